@@ -0,0 +1,113 @@
+//! Bot players filling up a match, every bot plays with a `BotStrategy`.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use rand::thread_rng;
+use rand::seq::{IteratorRandom, SliceRandom};
+
+use crate::cah_server::Card;
+
+/// How a bot plays, both while submitting and while being czar
+pub trait BotStrategy: Send + Sync {
+    // Shown as the name of the bot in the match
+    fn name(&self) -> &'static str;
+
+    // Pick `pick_count` cards from the hand, in the order they fill the blanks of the black card
+    fn choose_submission(&self, black_card: &Card, hand: &[Card], pick_count: usize) -> Vec<Card>;
+
+    // The index of the best submission, None when there are no submissions to choose from
+    fn choose_winner(&self, black_card: &Card, submissions: &[Vec<Card>]) -> Option<usize>;
+}
+
+/// Plays and judges without looking at the cards
+pub struct RandomStrategy;
+impl BotStrategy for RandomStrategy {
+    fn name(&self) -> &'static str {
+        "Random"
+    }
+
+    fn choose_submission(&self, _black_card: &Card, hand: &[Card], pick_count: usize) -> Vec<Card> {
+        hand.choose_multiple(&mut thread_rng(), pick_count).cloned().collect()
+    }
+
+    fn choose_winner(&self, _black_card: &Card, submissions: &[Vec<Card>]) -> Option<usize> {
+        (0..submissions.len()).choose(&mut thread_rng())
+    }
+}
+
+/// Prefers the cards sharing the most words with the black card
+pub struct KeywordStrategy;
+impl KeywordStrategy {
+    // Short words like "the" or "a" match everything, so only longer words count
+    const MIN_KEYWORD_LENGTH: usize = 4;
+
+    fn keywords(content: &str) -> HashSet<String> {
+        content.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.len() >= Self::MIN_KEYWORD_LENGTH)
+            .map(|word| word.to_lowercase())
+            .collect()
+    }
+
+    fn overlap(black_keywords: &HashSet<String>, cards: &[Card]) -> usize {
+        cards.iter().map(|card| Self::keywords(&card.content).intersection(black_keywords).count()).sum()
+    }
+}
+impl BotStrategy for KeywordStrategy {
+    fn name(&self) -> &'static str {
+        "Keyword"
+    }
+
+    fn choose_submission(&self, black_card: &Card, hand: &[Card], pick_count: usize) -> Vec<Card> {
+        let black_keywords = Self::keywords(&black_card.content);
+        let mut ranked_hand = hand.to_vec();
+        // Shuffle first, so cards without any overlap are still played in a random order
+        ranked_hand.shuffle(&mut thread_rng());
+        ranked_hand.sort_by_key(|card| std::cmp::Reverse(Self::overlap(&black_keywords, std::slice::from_ref(card))));
+        ranked_hand.truncate(pick_count);
+
+        ranked_hand
+    }
+
+    fn choose_winner(&self, black_card: &Card, submissions: &[Vec<Card>]) -> Option<usize> {
+        let black_keywords = Self::keywords(&black_card.content);
+        (0..submissions.len()).max_by_key(|index| Self::overlap(&black_keywords, &submissions[*index]))
+    }
+}
+
+/// Thinks the longest answer is always the funniest
+pub struct LongestAnswerStrategy;
+impl LongestAnswerStrategy {
+    fn answer_length(cards: &[Card]) -> usize {
+        cards.iter().map(|card| card.content.chars().count()).sum()
+    }
+}
+impl BotStrategy for LongestAnswerStrategy {
+    fn name(&self) -> &'static str {
+        "Longest Answer"
+    }
+
+    fn choose_submission(&self, _black_card: &Card, hand: &[Card], pick_count: usize) -> Vec<Card> {
+        let mut ranked_hand = hand.to_vec();
+        ranked_hand.sort_by_key(|card| std::cmp::Reverse(card.content.chars().count()));
+        ranked_hand.truncate(pick_count);
+
+        ranked_hand
+    }
+
+    fn choose_winner(&self, _black_card: &Card, submissions: &[Vec<Card>]) -> Option<usize> {
+        (0..submissions.len()).max_by_key(|index| Self::answer_length(&submissions[*index]))
+    }
+}
+
+/// The names clients can ask for when adding a bot
+pub const STRATEGY_NAMES: [&str; 3] = ["random", "keyword", "longestAnswer"];
+
+pub fn strategy_by_name(strategy_name: &str) -> Option<Arc<dyn BotStrategy>> {
+    match strategy_name {
+        "random" => Some(Arc::new(RandomStrategy)),
+        "keyword" => Some(Arc::new(KeywordStrategy)),
+        "longestAnswer" => Some(Arc::new(LongestAnswerStrategy)),
+        _ => None,
+    }
+}
@@ -3,27 +3,27 @@
 //! room through `ChatServer`.
 
 use actix::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use uuid::Uuid;
-use json::JsonValue;
 use std::sync::RwLock;
 use std::sync::Arc;
-use serde_json::json;
 use std::collections::hash_map::Entry;
 use num::PrimInt;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::fmt;
 use rusqlite::NO_PARAMS;
 use rusqlite::params;
 
 use std::u64;
 use crate::CookieToken;
 use crate::messages;
+use crate::messages::outgoing::ServerMessage;
 use crate::db;
+use crate::bots::{self, BotStrategy};
 
-use rand::distributions::WeightedIndex;
-use rand::distributions::Distribution;
 use rand::thread_rng;
 use rand::Rng;
+use rand::distributions::Alphanumeric;
 
 use sha2::Sha512;
 use sha2::Digest;
@@ -41,7 +41,54 @@ use crate::db::{Pool, Database};
 
 pub type CardId = i64;
 pub type PlayerId = i64;
+// Index into `Match::submissions` for the current round
+pub type SubmissionId = usize;
+// Teams are numbered from 0 up to `MatchSettings::teams`
+pub type TeamId = usize;
 const PlayerNilId: PlayerId = 0;
+// Real players get their id from the database, which never hands out negative ids
+pub const RANDO_CARDRISSIAN_ID: PlayerId = -1;
+// Bots added by the host count down from here
+const FIRST_BOT_ID: PlayerId = -2;
+// The black card of the final round with the Happy Ending house rule, it never comes from a deck
+const HAIKU_CARD_ID: CardId = -1;
+// Blank white cards never come from a deck either, they count down from here
+const FIRST_BLANK_CARD_ID: CardId = -2;
+// How long a bot czar waits before revealing all submissions, and again before picking the winner
+const BOT_CZAR_DELAY: Duration = Duration::from_millis(2000);
+// How long a bot takes to eliminate a submission with the Survival of the Fittest house rule
+const BOT_ELIMINATION_DELAY: Duration = Duration::from_millis(1500);
+// How long the bots take to vote with the God Is Dead house rule
+const BOT_VOTE_DELAY: Duration = Duration::from_millis(1500);
+// How long a player whose socket closed keeps their seat, after that they leave the match
+const RECONNECT_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+// The least amount of players needed before a match can be started
+pub const MIN_PLAYERS: u32 = 3;
+// The upper bound for `MatchSettings::max_players`
+pub const MAX_PLAYERS_LIMIT: u32 = 32;
+pub const MAX_MATCH_NAME_LENGTH: usize = 64;
+// The database stores the content of a card as a VARCHAR(255)
+pub const MAX_CARD_CONTENT_LENGTH: usize = 255;
+// The most white cards a black card can ask for, every hand holds at least this many cards
+pub const MAX_PICK: u32 = 3;
+// Write-ins are shown next to the other cards, so they are kept a lot shorter
+pub const MAX_WRITE_IN_LENGTH: usize = 100;
+// The upper bound for `MatchSettings::blank_cards`
+pub const MAX_BLANK_CARDS: u32 = 50;
+// With only two teams the czar's team sits out and a single submission is left to judge
+pub const MIN_TEAMS: u32 = 3;
+pub const MAX_TEAMS: u32 = 8;
+// For the match chat and the team chat alike
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 500;
+// A player can send `CHAT_RATE_LIMIT` chat messages every `CHAT_RATE_WINDOW`, team messages included
+pub const CHAT_RATE_LIMIT: usize = 5;
+pub const CHAT_RATE_WINDOW: Duration = Duration::from_secs(10);
+// How many of the latest chat messages new and resyncing clients get to read
+pub const CHAT_HISTORY_SIZE: usize = 50;
+pub const INVITE_CODE_LENGTH: usize = 8;
+// How many of the latest events of a match are kept, clients missing more than that get a new game state instead
+pub const REPLAY_BUFFER_SIZE: usize = 200;
 
 type ShaImpl = Sha512;
 //TODO: Infer this from ShaImpl::OutputSize instead of hardcoding
@@ -52,6 +99,8 @@ pub type PasswordHash = GenericArray<u8, <ShaImpl as Digest>::OutputSize>;
 pub struct Card {
     pub content: String,
     pub id: CardId,
+    // The amount of blanks on a black card, so how many white cards should be submitted for it. Always 1 for white cards
+    pub pick: u32,
 }
 
 impl Card {
@@ -64,115 +113,1772 @@ impl Card {
     pub fn is_black_card(&self) -> bool {
         return !self.is_white_card();
     }
+
+    // The amount of blanks (a run of underscores) in the content of a black card, at least 1 because a card without blanks still asks for an answer.
+    pub fn count_blanks(content: &str) -> u32 {
+        let blanks = content.split(|c| c != '_').filter(|part| !part.is_empty()).count() as u32;
+        blanks.max(1)
+    }
+
+    // Whether the white card is a blank one the player writes their own answer on when playing it
+    pub fn is_blank(&self) -> bool {
+        self.id <= FIRST_BLANK_CARD_ID
+    }
+
+    // The checks every card content has to pass before it ends up in a deck or in a match
+    pub fn validate_content(content: &str) -> Result<(), String> {
+        if content.trim().is_empty() {
+            return Err(str!("The content of a card can't be empty"));
+        }
+        if content.chars().count() > MAX_CARD_CONTENT_LENGTH {
+            return Err(format!("The content of a card can't be longer than {} characters", MAX_CARD_CONTENT_LENGTH));
+        }
+        if content.chars().any(char::is_control) {
+            return Err(str!("The content of a card can't contain control characters like line breaks"));
+        }
+
+        Ok(())
+    }
+
+    // A black card has to ask for at least one white card, but not for more than anyone can play
+    pub fn validate_pick(pick: u32) -> Result<(), String> {
+        if !(1..=MAX_PICK).contains(&pick) {
+            return Err(format!("A black card should ask for 1 to {} white cards, but asks for: {}", MAX_PICK, pick));
+        }
+
+        Ok(())
+    }
+}
+
+
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct CardDeck {
+    pub deck_name: String,
+    pub black_cards: Vec<Card>,
+    pub white_cards: Vec<Card>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+    pub id: PlayerId,
+}
+
+/// The phase a match is in, every action of a player is checked against it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchPhase {
+    // Waiting in the room for the host to start the match
+    #[default]
+    Lobby,
+    // Hands are refilled and the next black card is drawn
+    Dealing,
+    // Everyone except the czar picks white cards for the black card
+    Submitting,
+    // The czar turns over the submissions one by one
+    Revealing,
+    // Every submission is revealed, waiting for the czar to pick the best one, or for everyone to vote with God Is Dead
+    Judging,
+    // The round winner is known, the next round starts shortly
+    RoundResults,
+    // Someone reached `MatchSettings::points_to_win`
+    GameOver,
+}
+
+/// Why an action of a player in a match got rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MatchError {
+    NotLoggedIn,
+    MatchNotFound(String),
+    NotInMatch,
+    // The action doesn't fit the phase the match is in right now
+    WrongPhase{expected: MatchPhase, actual: MatchPhase},
+    NotHost,
+    NotCzar,
+    NotEnoughPlayers{needed: u32, actual: u32},
+    CardNotInHand(CardId),
+    // The czar judges the round, so doesn't play any cards
+    CzarCannotSubmit,
+    WrongCardCount{expected: u32, actual: u32},
+    DuplicateCard(CardId),
+    AlreadySubmitted,
+    UnknownSubmission(SubmissionId),
+    SpectatorsCannotPlay,
+    PlayerNotFound(PlayerId),
+    CannotKickYourself,
+    InvalidSettings(String),
+    // Rando Cardrissian comes and goes with `HouseRules::rando_cardrissian`
+    NotForRandoCardrissian,
+    MatchFull,
+    UnknownBotStrategy(String),
+    NotForBots,
+    // The name of the house rule the action needs
+    HouseRuleOff(&'static str),
+    // The name of the house rule which replaces the action
+    ReplacedByHouseRule(&'static str),
+    NotEnoughPoints,
+    NotYourTurn,
+    CannotVoteForYourself,
+    AlreadyVoted,
+    // Every blank card needs exactly one write-in
+    WrongWriteInCount{expected: u32, actual: u32},
+    InvalidWriteIn(String),
+    // The match is played without teams
+    NoTeamPlay,
+    UnknownTeam(TeamId),
+    NotEnoughTeams{needed: u32, actual: u32},
+    // Someone else in the team already submitted the cards of the team
+    TeamAlreadySubmitted,
+    InvalidChatMessage(String),
+    // How long the player has to wait before chatting again
+    ChatRateLimited(Duration),
+    // The websocket message isn't json, or doesn't match any `messages::incomming::ClientMessage`
+    MalformedMessage(String),
+    // Anything else was sent before `messages::incomming::ClientMessage::Hello`
+    HelloRequired,
+    AlreadySaidHello,
+}
+impl MatchError {
+    // A stable name clients can match on, the `Display` text is meant for people
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MatchError::NotLoggedIn => "notLoggedIn",
+            MatchError::MatchNotFound(_) => "matchNotFound",
+            MatchError::NotInMatch => "notInMatch",
+            MatchError::WrongPhase{..} => "wrongPhase",
+            MatchError::NotHost => "notHost",
+            MatchError::NotCzar => "notCzar",
+            MatchError::NotEnoughPlayers{..} => "notEnoughPlayers",
+            MatchError::CardNotInHand(_) => "cardNotInHand",
+            MatchError::CzarCannotSubmit => "czarCannotSubmit",
+            MatchError::WrongCardCount{..} => "wrongCardCount",
+            MatchError::DuplicateCard(_) => "duplicateCard",
+            MatchError::AlreadySubmitted => "alreadySubmitted",
+            MatchError::UnknownSubmission(_) => "unknownSubmission",
+            MatchError::SpectatorsCannotPlay => "spectatorsCannotPlay",
+            MatchError::PlayerNotFound(_) => "playerNotFound",
+            MatchError::CannotKickYourself => "cannotKickYourself",
+            MatchError::InvalidSettings(_) => "invalidSettings",
+            MatchError::NotForRandoCardrissian => "notForRandoCardrissian",
+            MatchError::MatchFull => "matchFull",
+            MatchError::UnknownBotStrategy(_) => "unknownBotStrategy",
+            MatchError::NotForBots => "notForBots",
+            MatchError::HouseRuleOff(_) => "houseRuleOff",
+            MatchError::ReplacedByHouseRule(_) => "replacedByHouseRule",
+            MatchError::NotEnoughPoints => "notEnoughPoints",
+            MatchError::NotYourTurn => "notYourTurn",
+            MatchError::CannotVoteForYourself => "cannotVoteForYourself",
+            MatchError::AlreadyVoted => "alreadyVoted",
+            MatchError::WrongWriteInCount{..} => "wrongWriteInCount",
+            MatchError::InvalidWriteIn(_) => "invalidWriteIn",
+            MatchError::NoTeamPlay => "noTeamPlay",
+            MatchError::UnknownTeam(_) => "unknownTeam",
+            MatchError::NotEnoughTeams{..} => "notEnoughTeams",
+            MatchError::TeamAlreadySubmitted => "teamAlreadySubmitted",
+            MatchError::InvalidChatMessage(_) => "invalidChatMessage",
+            MatchError::ChatRateLimited(_) => "chatRateLimited",
+            MatchError::MalformedMessage(_) => "malformedMessage",
+            MatchError::HelloRequired => "helloRequired",
+            MatchError::AlreadySaidHello => "alreadySaidHello",
+        }
+    }
+
+    // @arg action the type of the message which got rejected, so the client knows what to undo
+    // @arg request_id the id the client sent along with the message, see `ClientMessage`
+    pub fn to_message(&self, action: &str, request_id: Option<u64>) -> ServerMessage {
+        ServerMessage::Error {
+            action: action.to_string(),
+            request_id,
+            error: self.kind(),
+            message: self.to_string(),
+        }
+    }
+}
+impl fmt::Display for MatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatchError::NotLoggedIn => write!(f, "No user with that cookie token could be found, maybe the session expired?"),
+            MatchError::MatchNotFound(match_name) => write!(f, "Cannot find the match named '{}'", match_name),
+            MatchError::NotInMatch => write!(f, "You are not a player in this match"),
+            MatchError::WrongPhase{expected, actual} => write!(f, "This can only be done while the match is in the {:?} phase, but it is in the {:?} phase", expected, actual),
+            MatchError::NotHost => write!(f, "Only the host can do this"),
+            MatchError::NotCzar => write!(f, "Only the czar can do this"),
+            MatchError::NotEnoughPlayers{needed, actual} => write!(f, "At least {} players are needed, but there are only {}", needed, actual),
+            MatchError::CardNotInHand(card_id) => write!(f, "The card with id: {} is not in your hand", card_id),
+            MatchError::CzarCannotSubmit => write!(f, "The czar doesn't submit cards, but picks the best submission"),
+            MatchError::WrongCardCount{expected, actual} => write!(f, "The black card asks for {} cards, but {} were submitted", expected, actual),
+            MatchError::DuplicateCard(card_id) => write!(f, "The card with id: {} was submitted more than once", card_id),
+            MatchError::AlreadySubmitted => write!(f, "You already submitted cards this round"),
+            MatchError::UnknownSubmission(submission_id) => write!(f, "There is no submission with id: {}", submission_id),
+            MatchError::SpectatorsCannotPlay => write!(f, "You are watching this match, join it to play along"),
+            MatchError::PlayerNotFound(player_id) => write!(f, "There is no player with id: {} in this match", player_id),
+            MatchError::CannotKickYourself => write!(f, "The host can't kick themselves, leave the match instead"),
+            MatchError::InvalidSettings(reason) => write!(f, "These settings can't be used: {}", reason),
+            MatchError::NotForRandoCardrissian => write!(f, "Rando Cardrissian is a house rule, turn it on or off in the match settings instead"),
+            MatchError::MatchFull => write!(f, "There are no free seats left in this match"),
+            MatchError::UnknownBotStrategy(strategy_name) => write!(f, "There is no bot strategy named '{}', pick one of: {}", strategy_name, bots::STRATEGY_NAMES.join(", ")),
+            MatchError::NotForBots => write!(f, "Bots can't do this"),
+            MatchError::HouseRuleOff(house_rule) => write!(f, "This needs the house rule '{}', which is off in this match", house_rule),
+            MatchError::ReplacedByHouseRule(house_rule) => write!(f, "The house rule '{}' does this differently in this match", house_rule),
+            MatchError::NotEnoughPoints => write!(f, "You don't have a point to trade in"),
+            MatchError::NotYourTurn => write!(f, "It is someone else's turn"),
+            MatchError::CannotVoteForYourself => write!(f, "You can't vote for your own submission"),
+            MatchError::AlreadyVoted => write!(f, "You already voted this round"),
+            MatchError::WrongWriteInCount{expected, actual} => write!(f, "{} blank cards were submitted, but {} write-ins", expected, actual),
+            MatchError::InvalidWriteIn(reason) => write!(f, "This write-in can't be used: {}", reason),
+            MatchError::NoTeamPlay => write!(f, "This match is played without teams"),
+            MatchError::UnknownTeam(team) => write!(f, "There is no team {} in this match", team),
+            MatchError::NotEnoughTeams{needed, actual} => write!(f, "At least {} teams with players are needed, but there are only {}", needed, actual),
+            MatchError::TeamAlreadySubmitted => write!(f, "Someone in your team already submitted cards this round"),
+            MatchError::InvalidChatMessage(reason) => write!(f, "This message can't be sent: {}", reason),
+            MatchError::ChatRateLimited(wait) => write!(f, "You are chatting too fast, wait {} more seconds", wait.as_secs() + 1),
+            MatchError::MalformedMessage(reason) => write!(f, "This message can't be read: {}", reason),
+            MatchError::HelloRequired => write!(f, "Say hello with the protocol version first"),
+            MatchError::AlreadySaidHello => write!(f, "This socket already said hello"),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct PlayerInMatch {
+    player: Player,
+    cards: Vec<Card>,
+    points: u32,
+    // The cards submitted this round, in the order they fill the blanks of the black card. Empty if nothing was submitted yet
+    submitted_cards: Vec<Card>,
+    socket_actor: Option<Addr<crate::MyWebSocket>>,
+    // When the socket of the player closed, None while they are connected
+    disconnected_at: Option<Instant>,
+    // How the server plays for this player, None for people
+    bot: Option<Arc<dyn BotStrategy>>,
+    // The team the player is in when the match is played in teams, see `MatchSettings::teams`
+    team: Option<TeamId>,
+    // When the player sent their latest chat messages, oldest first and at most `CHAT_RATE_LIMIT` of them
+    recent_chats: VecDeque<Instant>,
+}
+impl PlayerInMatch {
+    /// The house rule player who plays random cards, see `HouseRules::rando_cardrissian`
+    pub fn new_rando_cardrissian() -> Self {
+        PlayerInMatch {
+            player: Player{name: str!("Rando Cardrissian"), id: RANDO_CARDRISSIAN_ID},
+            bot: Some(Arc::new(bots::RandomStrategy)),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_bot(id: PlayerId, strategy: Arc<dyn BotStrategy>) -> Self {
+        // Bot ids count down from `FIRST_BOT_ID`, so the first bot is number 1
+        let bot_number = FIRST_BOT_ID - id + 1;
+        PlayerInMatch {
+            player: Player{name: format!("{} Bot {}", strategy.name(), bot_number), id},
+            bot: Some(strategy),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_rando(&self) -> bool {
+        self.player.id == RANDO_CARDRISSIAN_ID
+    }
+
+    // Rando Cardrissian is a bot as well
+    pub fn is_bot(&self) -> bool {
+        self.bot.is_some()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.socket_actor.is_some()
+    }
+}
+
+/// The cards one player submitted in a round, once everyone submitted they get shuffled so the czar can't tell who played what.
+#[derive(Default, Clone)]
+pub struct Submission {
+    player_id: PlayerId,
+    cards: Vec<Card>,
+    revealed: bool,
+    // Thrown out by a player with the Survival of the Fittest house rule
+    eliminated: bool,
+}
+
+/// Who a match event is meant for
+#[derive(Clone, PartialEq, Eq)]
+pub enum Audience {
+    // Every player, everyone on the waitlist and every spectator
+    Everyone,
+    Players(Vec<PlayerId>),
+}
+impl Audience {
+    fn includes(&self, player_id: PlayerId) -> bool {
+        match self {
+            Audience::Everyone => true,
+            Audience::Players(player_ids) => player_ids.contains(&player_id),
+        }
+    }
+}
+
+/// An event which was sent in a match, kept so clients which lost their socket can catch up
+pub struct BufferedEvent {
+    seq: u64,
+    audience: Audience,
+    // Already numbered with `seq`
+    message: messages::outgoing::Message,
+}
+
+// Milliseconds since the unix epoch, deadlines are send to clients like this
+fn unix_time_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_millis() as u64).unwrap_or(0)
+}
+
+// Increment by one (unchecked) and then wrap it to `wrap_to` if the new value is equal to `wrap_from`
+//
+// @arg value is the value being wrapped if it's to big
+// @arg wrap_from if the `value`  reaches this number (inclusive) then the returned number will be equal to `wrap_to`
+// @arg wrap_to the value being wrapped to once `value` reaches `wrap_from`
+fn increment_and_wrap<T: PrimInt>(value: T, wrap_from: T, wrap_to: T) -> T {
+    assert!(wrap_from > wrap_to);
+
+    let new_val = value + T::one();
+    if new_val >= wrap_from {
+        wrap_to
+    } else {
+        new_val
+    }
+}
+
+/// A shuffled pile to draw cards from, together with the pile of cards which have been played.
+/// Once the draw pile runs out the discards are shuffled back in, so every card gets dealt once per cycle.
+#[derive(Default)]
+pub struct CardPile {
+    draw_pile: Vec<Card>,
+    discard_pile: Vec<Card>,
+}
+impl CardPile {
+    pub fn new(mut cards: Vec<Card>) -> Self {
+        cards.shuffle(&mut thread_rng());
+
+        CardPile{ draw_pile: cards, discard_pile: Vec::new() }
+    }
+
+    pub fn draw(&mut self) -> Option<Card> {
+        if self.draw_pile.is_empty() {
+            std::mem::swap(&mut self.draw_pile, &mut self.discard_pile);
+            self.draw_pile.shuffle(&mut thread_rng());
+        }
+
+        self.draw_pile.pop()
+    }
+
+    // Blank cards lose their write-in, so they come back blank
+    pub fn discard(&mut self, mut card: Card) {
+        if card.is_blank() {
+            card.content.clear();
+        }
+        self.discard_pile.push(card);
+    }
+
+    // All cards in the pile, drawn or discarded
+    pub fn card_count(&self) -> usize {
+        self.draw_pile.len() + self.discard_pile.len()
+    }
+}
+
+/// The optional rules from the official rule book, all of them are off by default
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HouseRules {
+    // A virtual player who submits random cards every round, but is never czar
+    pub rando_cardrissian: bool,
+    // Once someone reached the points to win, everyone plays one last "Make a haiku" round for the honour
+    pub happy_ending: bool,
+    // Players can trade in a point to swap their whole hand for new cards
+    pub rebooting_the_universe: bool,
+    // Everyone draws an extra card before playing a black card which asks for 2 cards
+    pub packing_heat: bool,
+    // Players can discard a card they don't understand, but everyone gets to see which one
+    pub never_have_i_ever: bool,
+    // Instead of the czar picking, the players take turns eliminating submissions until one is left
+    pub survival_of_the_fittest: bool,
+    // Nobody is czar, everyone plays and then votes for their favourite submission
+    pub god_is_dead: bool,
+    // Who wins a God Is Dead round when the most votes are tied
+    pub vote_tiebreak: VoteTiebreak,
+}
+
+/// How a tie for the most votes is broken with the God Is Dead house rule
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteTiebreak {
+    // One of the tied submissions wins at random
+    #[default]
+    Random,
+    // Every tied submission gets a point
+    AllTied,
+    // Nobody gets a point
+    Nobody,
+}
+
+/// The options a match gets created with, the defaults follow the official rules
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchSettings {
+    // The names of the card decks the cards get drawn from
+    pub active_decks: Vec<String>,
+    pub points_to_win: u32,
+    // The amount of white cards every player holds
+    pub hand_size: u32,
+    pub max_players: u32,
+    // How long the players get to submit their cards, after that random cards are played for them
+    pub submit_seconds: u32,
+    // How long the czar gets to reveal and judge the submissions, after that the round is skipped
+    pub judge_seconds: u32,
+    // The amount of blank cards mixed into the white cards, players write their own answer on them
+    pub blank_cards: u32,
+    // The deck the winning write-ins get added to, None to forget them after the round
+    pub write_in_deck: Option<String>,
+    // The amount of teams the players are split into, 0 to play without teams
+    pub teams: u32,
+    pub house_rules: HouseRules,
+}
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            active_decks: vec![str!("Default")],
+            points_to_win: 7,
+            hand_size: 10,
+            max_players: 10,
+            submit_seconds: 60,
+            judge_seconds: 60,
+            blank_cards: 0,
+            write_in_deck: None,
+            teams: 0,
+            house_rules: Default::default(),
+        }
+    }
 }
+impl MatchSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.active_decks.is_empty() {
+            return Err(str!("A match needs at least one card deck"));
+        }
+        if self.active_decks.iter().enumerate().any(|(i, deck_name)| self.active_decks[..i].contains(deck_name)) {
+            return Err(str!("Every card deck can only be used once in a match"));
+        }
+        if self.points_to_win == 0 {
+            return Err(str!("'points_to_win' should be at least 1"));
+        }
+        if self.hand_size < 3 || self.hand_size > 20 {
+            return Err(format!("'hand_size' should be between 3 and 20, but is: {}", self.hand_size));
+        }
+        if self.max_players < MIN_PLAYERS || self.max_players > MAX_PLAYERS_LIMIT {
+            return Err(format!("'max_players' should be between {} and {}, but is: {}", MIN_PLAYERS, MAX_PLAYERS_LIMIT, self.max_players));
+        }
+        if self.submit_seconds < 10 || self.submit_seconds > 600 {
+            return Err(format!("'submit_seconds' should be between 10 and 600, but is: {}", self.submit_seconds));
+        }
+        if self.judge_seconds < 10 || self.judge_seconds > 600 {
+            return Err(format!("'judge_seconds' should be between 10 and 600, but is: {}", self.judge_seconds));
+        }
+        if self.blank_cards > MAX_BLANK_CARDS {
+            return Err(format!("'blank_cards' should be at most {}, but is: {}", MAX_BLANK_CARDS, self.blank_cards));
+        }
+        if let Some(write_in_deck) = &self.write_in_deck {
+            if write_in_deck.is_empty() || write_in_deck.len() > MAX_MATCH_NAME_LENGTH {
+                return Err(format!("'write_in_deck' should be between 1 and {} characters long", MAX_MATCH_NAME_LENGTH));
+            }
+        }
+        if self.teams != 0 && (self.teams < MIN_TEAMS || self.teams > MAX_TEAMS) {
+            return Err(format!("'teams' should be 0 to play without teams, or between {} and {}, but is: {}", MIN_TEAMS, MAX_TEAMS, self.teams));
+        }
+        if self.teams != 0 && self.house_rules.god_is_dead {
+            return Err(str!("The house rule 'god_is_dead' lets every player vote on their own, so it can't be played in teams"));
+        }
+        if self.house_rules.god_is_dead && self.house_rules.survival_of_the_fittest {
+            return Err(str!("The house rules 'god_is_dead' and 'survival_of_the_fittest' both replace the czar, only one can be used"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Who besides the players already in it can join a match
+#[derive(Default)]
+pub enum MatchAccess {
+    #[default]
+    Public,
+    // Hashed the same way as account passwords, with a salt of its own
+    Password{salt: Uuid, password_hash: PasswordHash},
+    // Hidden from the match list, only players with the code the server generated can join
+    InviteCode(String),
+}
+impl MatchAccess {
+    pub fn with_password(password: &str) -> Self {
+        let salt = Uuid::new_v4();
+        MatchAccess::Password{salt, password_hash: hash_password(&salt, password)}
+    }
+
+    pub fn with_new_invite_code() -> Self {
+        let invite_code: String = thread_rng().sample_iter(&Alphanumeric).take(INVITE_CODE_LENGTH).collect();
+        MatchAccess::InviteCode(invite_code)
+    }
+
+    pub fn is_public(&self) -> bool {
+        matches!(self, MatchAccess::Public)
+    }
+
+    pub fn invite_code(&self) -> Option<String> {
+        match self {
+            MatchAccess::InviteCode(invite_code) => Some(invite_code.clone()),
+            _ => None,
+        }
+    }
+}
+
+pub struct Match {
+    players: Vec<PlayerInMatch>,
+    phase: MatchPhase,
+    settings: MatchSettings,
+    czar: PlayerId,
+    // The player allowed to start and configure the match, the creator at first
+    host: PlayerId,
+    black_card: Option<Card>,
+    black_pile: CardPile,
+    white_pile: CardPile,
+    submissions: Vec<Submission>,
+    // When the countdown of the current phase runs out, in milliseconds since the unix epoch. None if nobody is being waited on
+    deadline: Option<u64>,
+    // The future counting down in the `CahServer` context, with the deadline it was started for
+    timer: Option<(SpawnHandle, u64)>,
+    // Sockets watching the match, they receive everything everyone can see but never anyone's hand
+    spectators: Vec<Addr<crate::MyWebSocket>>,
+    // A locked match only lets in the players who are already in it
+    locked: bool,
+    // Players kicked by the host, they can't join this match again
+    banned: HashSet<PlayerId>,
+    access: MatchAccess,
+    // Players waiting for a seat once the match is full, they watch the match until they get promoted between rounds
+    waitlist: VecDeque<PlayerInMatch>,
+    // The id the next bot added by the host gets
+    next_bot_id: PlayerId,
+    // The deadline a bot czar was last scheduled to judge for, so it is scheduled once per round
+    bot_czar_deadline: Option<u64>,
+    // The "Make a haiku" round of the Happy Ending house rule, the game ends after it
+    final_round: bool,
+    // Whose turn it is to eliminate a submission with the Survival of the Fittest house rule
+    eliminating_player: PlayerId,
+    // The submission every player voted for with the God Is Dead house rule
+    votes: HashMap<PlayerId, SubmissionId>,
+    // The points of every team when the match is played in teams, indexed by `TeamId`
+    team_points: Vec<u32>,
+    // The player of every team who was czar the last time the team had its turn, so the czar rotates within the team as well
+    last_team_czars: Vec<PlayerId>,
+    // The number the next event gets, events are numbered from 1 so a client which saw nothing can ask for everything after 0
+    next_seq: u64,
+    // The latest `REPLAY_BUFFER_SIZE` events, oldest first
+    replay_buffer: VecDeque<BufferedEvent>,
+    // The latest `CHAT_HISTORY_SIZE` chat messages, oldest first
+    chat_history: VecDeque<ChatEntry>,
+}
+impl Default for Match{
+    fn default() -> Self {
+        Match {
+            players: Default::default(),
+            phase: Default::default(),
+            settings: Default::default(),
+            czar: PlayerNilId,
+            host: PlayerNilId,
+            black_card: None,
+            black_pile: Default::default(),
+            white_pile: Default::default(),
+            submissions: Vec::new(),
+            deadline: None,
+            timer: None,
+            spectators: Vec::new(),
+            locked: false,
+            banned: HashSet::new(),
+            access: MatchAccess::Public,
+            waitlist: VecDeque::new(),
+            next_bot_id: FIRST_BOT_ID,
+            bot_czar_deadline: None,
+            final_round: false,
+            eliminating_player: PlayerNilId,
+            votes: HashMap::new(),
+            team_points: Vec::new(),
+            last_team_czars: Vec::new(),
+            next_seq: 1,
+            replay_buffer: VecDeque::new(),
+            chat_history: VecDeque::new(),
+        }
+    }
+}
+impl Match {
+    pub fn new(settings: MatchSettings, host: PlayerId, access: MatchAccess) -> Self {
+        let mut new_match = Match {
+            settings,
+            host,
+            access,
+            ..Default::default()
+        };
+        new_match.reassign_teams();
+        new_match.update_rando_cardrissian();
+
+        new_match
+    }
+
+    // Add or remove Rando Cardrissian so it matches the settings
+    fn update_rando_cardrissian(&mut self) {
+        let rando_pos_opt = self.players.iter().position(|pim| pim.is_rando());
+        match (self.settings.house_rules.rando_cardrissian, rando_pos_opt) {
+            (true, None) => {
+                let rando = PlayerInMatch::new_rando_cardrissian();
+                let player_joined_message = ServerMessage::PlayerJoined {
+                    player: rando.player.clone(),
+                };
+                let joined_chat = format!("{} joined the match", rando.player.name);
+                self.players.push(rando);
+                self.send_to_all_players(player_joined_message);
+                self.send_system_message(joined_chat);
+                self.assign_team(self.players.len() - 1);
+            },
+            (false, Some(_)) => {
+                self.leave(&RANDO_CARDRISSIAN_ID);
+            },
+            _ => {},
+        }
+    }
+
+    // The first player from `start_index` on (wrapping around) who can be czar, Rando Cardrissian never is. With God Is Dead nobody is
+    fn next_eligible_czar(&self, start_index: usize) -> PlayerId {
+        if self.settings.house_rules.god_is_dead {
+            return PlayerNilId;
+        }
+        let player_count = self.players.len();
+        (0..player_count)
+            .map(|offset| &self.players[(start_index + offset) % player_count])
+            .find(|pim| !pim.is_rando())
+            .map_or(PlayerNilId, |pim| pim.player.id)
+    }
+
+    // Everyone except the czar plays cards, when God Is Dead there is no czar so everyone plays. In teams the whole team of the czar sits the round out
+    fn plays_this_round(&self, player_id: PlayerId) -> bool {
+        if self.czar == PlayerNilId {
+            return true;
+        }
+        match (self.team_of(player_id), self.team_of(self.czar)) {
+            (Some(team), Some(czar_team)) => team != czar_team,
+            _ => player_id != self.czar,
+        }
+    }
+
+    fn team_of(&self, player_id: PlayerId) -> Option<TeamId> {
+        self.players.iter().find(|pim| pim.player.id == player_id).and_then(|pim| pim.team)
+    }
+
+    fn team_members(&self, team: TeamId) -> impl Iterator<Item = &PlayerInMatch> {
+        self.players.iter().filter(move |pim| pim.team == Some(team))
+    }
+
+    // Whether the player, or with teams anyone in the team of the player, submitted cards this round
+    fn has_submitted(&self, player_id: PlayerId) -> bool {
+        match self.team_of(player_id) {
+            Some(team) => self.team_members(team).any(|pim| !pim.submitted_cards.is_empty()),
+            None => self.players.iter().any(|pim| pim.player.id == player_id && !pim.submitted_cards.is_empty()),
+        }
+    }
+
+    // Put a new player in the team with the fewest players and let everyone know, without teams nothing happens
+    fn assign_team(&mut self, player_index: usize) {
+        if self.settings.teams == 0 {
+            return;
+        }
+        let team = (0..self.settings.teams as TeamId)
+            .min_by_key(|team| self.players.iter().filter(|pim| pim.team == Some(*team)).count())
+            .expect("There is at least one team when playing in teams");
+        self.set_team(player_index, team);
+    }
+
+    fn set_team(&mut self, player_index: usize, team: TeamId) {
+        self.players[player_index].team = Some(team);
+        let team_changed_message = ServerMessage::TeamChanged {
+            player_id: self.players[player_index].player.id,
+            team,
+        };
+        self.send_to_all_players(team_changed_message);
+    }
+
+    // Split everyone evenly over the teams again, after the amount of teams changed
+    fn reassign_teams(&mut self) {
+        let team_count = self.settings.teams as usize;
+        self.team_points = vec![0; team_count];
+        self.last_team_czars = vec![PlayerNilId; team_count];
+        for player_index in 0..self.players.len() {
+            if team_count == 0 {
+                self.players[player_index].team = None;
+            } else {
+                self.set_team(player_index, player_index % team_count);
+            }
+        }
+    }
+
+    // Players pick their own team before the match starts
+    fn join_team(&mut self, player_id: PlayerId, team: TeamId) -> Result<(), MatchError> {
+        if self.settings.teams == 0 {
+            return Err(MatchError::NoTeamPlay);
+        }
+        self.expect_phase(MatchPhase::Lobby)?;
+        if team >= self.settings.teams as TeamId {
+            return Err(MatchError::UnknownTeam(team));
+        }
+        let player_index = self.players.iter().position(|pim| pim.player.id == player_id).ok_or(MatchError::NotInMatch)?;
+        self.set_team(player_index, team);
+
+        Ok(())
+    }
+
+    // The teams with at least one player in them
+    fn teams_with_players(&self) -> u32 {
+        (0..self.settings.teams as TeamId).filter(|team| self.team_members(*team).next().is_some()).count() as u32
+    }
+
+    // Team members see each other's hands, so they can decide together what to play
+    fn send_team_hands(&mut self, team: TeamId) {
+        let team_hands_message = ServerMessage::TeamHands {
+            hands: self.team_hands(team),
+        };
+        self.send_to_team(team, team_hands_message);
+    }
+
+    fn send_all_team_hands(&mut self) {
+        for team in 0..self.settings.teams as TeamId {
+            self.send_team_hands(team);
+        }
+    }
+
+    fn team_hands(&self, team: TeamId) -> Vec<TeamHand> {
+        self.team_members(team).map(|pim| TeamHand{player_id: pim.player.id, cards: pim.cards.clone()}).collect()
+    }
+
+    // Let the team know which cards one of them played for the team
+    fn send_team_submitted(&mut self, player_id: PlayerId) {
+        let team = match self.team_of(player_id) {
+            Some(team) => team,
+            None => return,
+        };
+        let submitted_cards = self.players.iter().find(|pim| pim.player.id == player_id).map(|pim| pim.submitted_cards.clone()).unwrap_or_default();
+        let team_submitted_message = ServerMessage::TeamSubmitted {
+            player_id,
+            cards: submitted_cards,
+        };
+        self.send_to_team(team, team_submitted_message);
+    }
+
+    // A message only the team of the player gets to see
+    fn send_team_message(&mut self, player_id: PlayerId, text: &str) -> Result<(), MatchError> {
+        if self.settings.teams == 0 {
+            return Err(MatchError::NoTeamPlay);
+        }
+        let team = self.team_of(player_id).ok_or(MatchError::NotInMatch)?;
+        let text = self.check_chat_message(player_id, text)?;
+
+        let team_message = ServerMessage::TeamMessage {
+            player_id,
+            text,
+        };
+        self.send_to_team(team, team_message);
+
+        Ok(())
+    }
+
+    // A message of a player or someone on the waitlist in the match chat
+    fn send_chat_message(&mut self, player_id: PlayerId, text: &str) -> Result<(), MatchError> {
+        let text = self.check_chat_message(player_id, text)?;
+        self.send_chat(Some(player_id), text);
+
+        Ok(())
+    }
+
+    // A message of the server in the match chat, e.g. who joined or won the round
+    fn send_system_message(&mut self, text: String) {
+        self.send_chat(None, text);
+    }
+
+    fn send_chat(&mut self, player_id: Option<PlayerId>, text: String) {
+        let chat_entry = ChatEntry {
+            player_id,
+            text,
+            sent_at: unix_time_millis(),
+        };
+        if self.chat_history.len() >= CHAT_HISTORY_SIZE {
+            self.chat_history.pop_front();
+        }
+        self.chat_history.push_back(chat_entry.clone());
+        self.send_to_all_players(ServerMessage::Chat(chat_entry));
+    }
+
+    // Trim the text of a chat message and make sure it can be sent, which counts towards the rate limit of the player
+    fn check_chat_message(&mut self, player_id: PlayerId, text: &str) -> Result<String, MatchError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(MatchError::InvalidChatMessage(str!("it can't be empty")));
+        }
+        if text.chars().count() > MAX_CHAT_MESSAGE_LENGTH {
+            return Err(MatchError::InvalidChatMessage(format!("it can't be longer than {} characters", MAX_CHAT_MESSAGE_LENGTH)));
+        }
+
+        let pim = self.players.iter_mut().chain(self.waitlist.iter_mut())
+            .find(|pim| pim.player.id == player_id)
+            .ok_or(MatchError::NotInMatch)?;
+        let now = Instant::now();
+        while pim.recent_chats.front().is_some_and(|sent_at| now.duration_since(*sent_at) >= CHAT_RATE_WINDOW) {
+            pim.recent_chats.pop_front();
+        }
+        if pim.recent_chats.len() >= CHAT_RATE_LIMIT {
+            let oldest_chat = pim.recent_chats[0];
+            return Err(MatchError::ChatRateLimited(CHAT_RATE_WINDOW - now.duration_since(oldest_chat)));
+        }
+        pim.recent_chats.push_back(now);
+
+        Ok(text.to_string())
+    }
+
+    // The player with the most points, or with teams the best player of the team with the most points
+    fn leader(&self) -> PlayerId {
+        let leading_team = (0..self.team_points.len()).max_by_key(|team| (self.team_points[*team], std::cmp::Reverse(*team)));
+        self.scoreboard().iter()
+            .find(|score| leading_team.is_none() || self.team_of(score.player.id) == leading_team)
+            .map_or(PlayerNilId, |score| score.player.id)
+    }
+
+    fn remove_player(&mut self, user_id: &PlayerId) -> Option<PlayerInMatch>{
+        let player_pos_option = self.players.iter().position(move |player| player.player.id == *user_id);
+        match player_pos_option {
+            Some(player_pos) => {
+                let white_card_count = self.white_card_count();
+                let player = self.players.remove(player_pos);
+                // The submitted cards are copies of cards which are still in the hand
+                for card in &player.cards {
+                    self.white_pile.discard(card.clone());
+                }
+                debug_assert_eq!(self.white_card_count(), white_card_count, "Cards got lost or duplicated when player: {} left", user_id);
+
+                // The player after the old czar takes over, they now sit at the same index
+                if self.czar == *user_id {
+                    self.czar = self.next_eligible_czar(player_pos);
+                }
+                if self.eliminating_player == *user_id {
+                    let next_player = if self.players.is_empty() { PlayerNilId } else { self.players[player_pos % self.players.len()].player.id };
+                    self.set_eliminating_player(next_player);
+                }
+                if self.host == *user_id {
+                    let new_host = self.players.iter().find(|pim| !pim.is_bot()).map_or(PlayerNilId, |pim| pim.player.id);
+                    self.set_host(new_host);
+                }
+
+                Some(player)
+            },
+            None => { None }
+        }
+    }
+
+    // The white cards in the pile and in everyone's hand, this stays the same while a match is played
+    fn white_card_count(&self) -> usize {
+        self.white_pile.card_count() + self.players.iter().map(|pim| pim.cards.len()).sum::<usize>()
+    }
+
+    // Remove a player or someone on the waitlist and let everyone else know they are gone
+    fn leave(&mut self, user_id: &PlayerId) -> Option<PlayerInMatch> {
+        if let Some(waitlist_pos) = self.waitlist.iter().position(|pim| pim.player.id == *user_id) {
+            let removed_player = self.waitlist.remove(waitlist_pos);
+            self.send_waitlist_positions();
+            return removed_player;
+        }
+
+        let removed_player = self.remove_player(user_id)?;
+        // The player who left might have been the last one everyone was waiting for
+        self.end_submitting_if_done();
+
+        let player_left_message = ServerMessage::PlayerLeft {
+            player_id: removed_player.player.id,
+        };
+        self.send_to_all_players(player_left_message);
+        self.send_system_message(format!("{} left the match", removed_player.player.name));
+
+        // Outside of a running game the free seat can be taken right away
+        if self.phase == MatchPhase::Lobby || self.phase == MatchPhase::GameOver {
+            self.promote_from_waitlist();
+        }
+
+        Some(removed_player)
+    }
+
+    // Seat a new bot playing with the strategy, bots only join through the host
+    fn add_bot(&mut self, strategy: Arc<dyn BotStrategy>) -> Result<PlayerId, MatchError> {
+        if self.is_full() {
+            return Err(MatchError::MatchFull);
+        }
+
+        let bot = PlayerInMatch::new_bot(self.next_bot_id, strategy);
+        self.next_bot_id -= 1;
+        let bot_id = bot.player.id;
+        let player_joined_message = ServerMessage::PlayerJoined {
+            player: bot.player.clone(),
+        };
+        let joined_chat = format!("{} joined the match", bot.player.name);
+        self.players.push(bot);
+        self.send_to_all_players(player_joined_message);
+        self.send_system_message(joined_chat);
+        self.assign_team(self.players.len() - 1);
+
+        Ok(bot_id)
+    }
+
+    fn is_full(&self) -> bool {
+        self.players.len() >= self.settings.max_players as usize
+    }
+
+    // 1 for the first one in line, None if the player isn't waiting
+    fn waitlist_position(&self, player_id: PlayerId) -> Option<usize> {
+        self.waitlist.iter().position(|pim| pim.player.id == player_id).map(|pos| pos + 1)
+    }
+
+    // Move players from the front of the waitlist into the free seats, they start with an empty hand which is filled when the next round is dealt
+    fn promote_from_waitlist(&mut self) {
+        if self.waitlist.is_empty() || self.is_full() {
+            return;
+        }
+
+        while !self.is_full() {
+            let promoted_player = match self.waitlist.pop_front() {
+                Some(promoted_player) => promoted_player,
+                None => break,
+            };
+            let promoted_id = promoted_player.player.id;
+            let player_joined_message = ServerMessage::PlayerJoined {
+                player: promoted_player.player.clone(),
+            };
+            let joined_chat = format!("{} joined the match from the waitlist", promoted_player.player.name);
+            self.players.push(promoted_player);
+            self.send_to_all_players(player_joined_message);
+            self.send_system_message(joined_chat);
+            self.assign_team(self.players.len() - 1);
+
+            // Everything changes for a promoted player, so sync the client up again
+            let game_state = self.game_state(promoted_id).expect("We just made the promoted player a player in this match");
+            if let Some(socket_actor) = &self.players.last().unwrap().socket_actor {
+                socket_actor.do_send(messages::outgoing::Message::from(ServerMessage::GameState(Box::new(game_state))));
+            }
+            println!("player: {} got promoted from the waitlist", promoted_id);
+        }
+
+        self.send_waitlist_positions();
+    }
+
+    // Let everyone on the waitlist know how far they are in line
+    fn send_waitlist_positions(&mut self) {
+        let waiting_ids: Vec<PlayerId> = self.waitlist.iter().map(|waiting_player| waiting_player.player.id).collect();
+        for (pos, waiting_id) in waiting_ids.into_iter().enumerate() {
+            let waitlist_position_message = ServerMessage::WaitlistPosition {
+                position: pos + 1,
+            };
+            self.send_to_player(waiting_id, waitlist_position_message);
+        }
+    }
+
+    fn set_host(&mut self, host: PlayerId) {
+        self.host = host;
+        let new_host_message = ServerMessage::NewHost {
+            host: self.host,
+        };
+        self.send_to_all_players(new_host_message);
+    }
+
+    fn expect_host(&self, user_id: PlayerId) -> Result<(), MatchError> {
+        if self.host == user_id {
+            Ok(())
+        } else {
+            Err(MatchError::NotHost)
+        }
+    }
+
+    // Whether the player may join this match, players already in it and the host can always rejoin
+    fn check_can_join(&self, match_name: &str, user_id: PlayerId, already_in_match: bool, password: Option<&str>, invite_code: Option<&str>) -> Result<(), String> {
+        if self.banned.contains(&user_id) {
+            return Err(format!("You have been kicked from the match '{}'", match_name));
+        }
+        if already_in_match || self.host == user_id {
+            return Ok(());
+        }
+        match &self.access {
+            MatchAccess::Public => {},
+            MatchAccess::Password{salt, password_hash} => match password {
+                Some(password) if hash_password(salt, password) == *password_hash => {},
+                Some(_) => return Err(format!("Wrong password for the match '{}'", match_name)),
+                None => return Err(format!("The match '{}' needs a password to join", match_name)),
+            },
+            MatchAccess::InviteCode(match_invite_code) => match invite_code {
+                Some(invite_code) if invite_code == match_invite_code => {},
+                Some(_) => return Err(format!("That invite code is not valid for the match '{}'", match_name)),
+                None => return Err(format!("The match '{}' can only be joined with an invite code", match_name)),
+            },
+        }
+        if self.locked {
+            return Err(format!("The match '{}' is locked", match_name));
+        }
+        Ok(())
+    }
+
+    fn expect_phase(&self, expected: MatchPhase) -> Result<(), MatchError> {
+        if self.phase == expected {
+            Ok(())
+        } else {
+            Err(MatchError::WrongPhase{expected, actual: self.phase})
+        }
+    }
+
+    fn set_phase(&mut self, phase: MatchPhase) {
+        self.phase = phase;
+        // Revealing and judging share one countdown, the czar has to finish both in time
+        self.deadline = match phase {
+            MatchPhase::Submitting => Some(unix_time_millis() + u64::from(self.settings.submit_seconds) * 1000),
+            MatchPhase::Revealing => Some(unix_time_millis() + u64::from(self.settings.judge_seconds) * 1000),
+            MatchPhase::Judging => self.deadline,
+            _ => None,
+        };
+
+        let new_phase_message = ServerMessage::NewPhase {
+            phase,
+            deadline: self.deadline,
+        };
+        self.send_to_all_players(new_phase_message);
+    }
+
+    // Refill everyone's hand and draw the next black card, after which the players can submit their cards
+    fn start_round(&mut self) {
+        self.set_phase(MatchPhase::Dealing);
+        self.refill_hands();
+        self.next_black_card();
+        if self.settings.house_rules.packing_heat && self.pick_count() == 2 {
+            let hand_size = self.settings.hand_size as usize + 1;
+            for player_index in 0..self.players.len() {
+                if self.plays_this_round(self.players[player_index].player.id) {
+                    self.refill_hand_to(player_index, hand_size);
+                }
+            }
+        }
+        self.send_all_team_hands();
+        self.submit_bot_cards();
+        self.set_phase(MatchPhase::Submitting);
+        // When the czar is the only person in the match, the bots already did all the submitting.
+        // Without anyone to play there is nothing to judge, so the round waits for the submit timer instead of being skipped over and over
+        if self.has_everyone_submitted_card() && self.has_any_submission() {
+            self.end_submitting();
+        }
+    }
+
+    // Bots make up their mind straight away, bots without enough cards are skipped just like players
+    fn submit_bot_cards(&mut self) {
+        let black_card = match &self.black_card {
+            Some(black_card) => black_card.clone(),
+            None => return,
+        };
+        let pick_count = self.pick_count() as usize;
+        for player_index in 0..self.players.len() {
+            let player_in_match = &self.players[player_index];
+            // Bots have nothing to write on blank cards
+            let playable_cards: Vec<Card> = player_in_match.cards.iter().filter(|card| !card.is_blank()).cloned().collect();
+            if !self.plays_this_round(player_in_match.player.id) || playable_cards.len() < pick_count || self.has_submitted(player_in_match.player.id) {
+                continue;
+            }
+            // Bots leave the choice to the people in their team
+            if let Some(team) = player_in_match.team {
+                if self.team_members(team).any(|pim| !pim.is_bot()) {
+                    continue;
+                }
+            }
+            let player_in_match = &mut self.players[player_index];
+            if let Some(strategy) = &player_in_match.bot {
+                player_in_match.submitted_cards = strategy.choose_submission(&black_card, &playable_cards, pick_count);
+            }
+        }
+    }
+
+    // Show the cards of a submission to everyone, once all are revealed the czar can judge them
+    fn reveal_submission(&mut self, submission_id: SubmissionId) -> Result<(), MatchError> {
+        let submission = self.submissions.get_mut(submission_id).ok_or(MatchError::UnknownSubmission(submission_id))?;
+        submission.revealed = true;
+
+        let reveal_card_message = ServerMessage::RevealCard {
+            submission_id,
+            cards: submission.cards.clone(),
+        };
+        self.send_to_all_players(reveal_card_message);
+
+        if self.submissions.iter().all(|submission| submission.revealed) {
+            self.set_phase(MatchPhase::Judging);
+            // The player after the czar throws out the first submission
+            if self.settings.house_rules.survival_of_the_fittest && !self.players.is_empty() {
+                let czar_index = self.players.iter().position(|pim| pim.player.id == self.czar).unwrap_or(0);
+                let first_player = self.players[increment_and_wrap(czar_index, self.players.len(), 0)].player.id;
+                self.set_eliminating_player(first_player);
+            }
+        }
+
+        Ok(())
+    }
+
+    // The submissions nobody eliminated yet
+    fn remaining_submissions(&self) -> Vec<SubmissionId> {
+        (0..self.submissions.len()).filter(|submission_id| !self.submissions[*submission_id].eliminated).collect()
+    }
+
+    // The last submission standing with the Survival of the Fittest house rule, it wins the round
+    fn survivor(&self) -> Option<SubmissionId> {
+        if !self.settings.house_rules.survival_of_the_fittest || self.phase != MatchPhase::Judging {
+            return None;
+        }
+
+        match self.remaining_submissions().as_slice() {
+            [survivor] => Some(*survivor),
+            _ => None,
+        }
+    }
+
+    fn set_eliminating_player(&mut self, player_id: PlayerId) {
+        self.eliminating_player = player_id;
+        let elimination_turn_message = ServerMessage::EliminationTurn {
+            player_id,
+        };
+        self.send_to_all_players(elimination_turn_message);
+    }
+
+    // Throw out a submission with the Survival of the Fittest house rule, then it is the next player's turn
+    fn eliminate_submission(&mut self, player_id: PlayerId, submission_id: SubmissionId) -> Result<(), MatchError> {
+        if !self.settings.house_rules.survival_of_the_fittest {
+            return Err(MatchError::HouseRuleOff("survivalOfTheFittest"));
+        }
+        self.expect_phase(MatchPhase::Judging)?;
+        if player_id != self.eliminating_player {
+            return Err(MatchError::NotYourTurn);
+        }
+        match self.submissions.get_mut(submission_id) {
+            Some(submission) if !submission.eliminated => submission.eliminated = true,
+            _ => return Err(MatchError::UnknownSubmission(submission_id)),
+        }
+
+        let submission_eliminated_message = ServerMessage::SubmissionEliminated {
+            submission_id,
+            player_id,
+        };
+        self.send_to_all_players(submission_eliminated_message);
+
+        let player_index_opt = self.players.iter().position(|pim| pim.player.id == player_id);
+        match player_index_opt {
+            Some(player_index) if self.survivor().is_none() => {
+                let next_player = self.players[increment_and_wrap(player_index, self.players.len(), 0)].player.id;
+                self.set_eliminating_player(next_player);
+            },
+            // The round is decided, or the player is gone and nobody is left to take the next turn
+            _ => self.eliminating_player = PlayerNilId,
+        }
+
+        Ok(())
+    }
+
+    // The eliminating player ran out of time, so a random submission is thrown out for them and the next player gets a new countdown
+    fn eliminate_for_timed_out_player(&mut self) {
+        let eliminating_player = self.eliminating_player;
+        let elimination_timed_out_message = ServerMessage::EliminationTimedOut {
+            player_id: eliminating_player,
+        };
+        self.send_to_all_players(elimination_timed_out_message);
+
+        if let Some(submission_id) = self.remaining_submissions().choose(&mut thread_rng()) {
+            let _ = self.eliminate_submission(eliminating_player, *submission_id);
+        }
+        if self.survivor().is_none() {
+            self.deadline = Some(unix_time_millis() + u64::from(self.settings.judge_seconds) * 1000);
+            let new_phase_message = ServerMessage::NewPhase {
+                phase: self.phase,
+                deadline: self.deadline,
+            };
+            self.send_to_all_players(new_phase_message);
+        }
+    }
+
+    // Trade in a point for a completely new hand with the Rebooting the Universe house rule
+    fn reboot_hand(&mut self, player_id: PlayerId) -> Result<(), MatchError> {
+        if !self.settings.house_rules.rebooting_the_universe {
+            return Err(MatchError::HouseRuleOff("rebootingTheUniverse"));
+        }
+        self.expect_phase(MatchPhase::Submitting)?;
+        let player_index = self.players.iter().position(|pim| pim.player.id == player_id).ok_or(MatchError::NotInMatch)?;
+        let player_in_match = &mut self.players[player_index];
+        if !player_in_match.submitted_cards.is_empty() {
+            return Err(MatchError::AlreadySubmitted);
+        }
+        if player_in_match.points == 0 {
+            return Err(MatchError::NotEnoughPoints);
+        }
+
+        player_in_match.points -= 1;
+        let points = player_in_match.points;
+        for card in std::mem::take(&mut player_in_match.cards) {
+            let remove_card_message = ServerMessage::RemoveCard {
+                card_id: card.id,
+            };
+            self.send_to_player(player_id, remove_card_message);
+            self.white_pile.discard(card);
+        }
+        self.refill_hand(player_index);
+
+        let hand_rebooted_message = ServerMessage::HandRebooted {
+            player_id,
+            points,
+        };
+        self.send_to_all_players(hand_rebooted_message);
+
+        Ok(())
+    }
+
+    // Swap a card for a new one with the Never Have I Ever house rule, everyone gets to see the confessed card
+    fn confess_card(&mut self, player_id: PlayerId, card_id: CardId) -> Result<(), MatchError> {
+        if !self.settings.house_rules.never_have_i_ever {
+            return Err(MatchError::HouseRuleOff("neverHaveIEver"));
+        }
+        self.expect_phase(MatchPhase::Submitting)?;
+        let player_index = self.players.iter().position(|pim| pim.player.id == player_id).ok_or(MatchError::NotInMatch)?;
+        let player_in_match = &mut self.players[player_index];
+        if !player_in_match.submitted_cards.is_empty() {
+            return Err(MatchError::AlreadySubmitted);
+        }
+        let card_pos = player_in_match.cards.iter().position(|card| card.id == card_id).ok_or(MatchError::CardNotInHand(card_id))?;
+
+        let confessed_card = player_in_match.cards.remove(card_pos);
+        let remove_card_message = ServerMessage::RemoveCard {
+            card_id,
+        };
+        self.send_to_player(player_id, remove_card_message);
+        let card_confessed_message = ServerMessage::CardConfessed {
+            player_id,
+            card: confessed_card.clone(),
+        };
+        self.white_pile.discard(confessed_card);
+        self.refill_hand(player_index);
+        self.send_to_all_players(card_confessed_message);
+
+        Ok(())
+    }
+
+    // Vote for the best submission with the God Is Dead house rule, nobody gets to see who voted for what
+    fn vote(&mut self, player_id: PlayerId, submission_id: SubmissionId) -> Result<(), MatchError> {
+        if !self.settings.house_rules.god_is_dead {
+            return Err(MatchError::HouseRuleOff("godIsDead"));
+        }
+        self.expect_phase(MatchPhase::Judging)?;
+        if !self.players.iter().any(|pim| pim.player.id == player_id) {
+            return Err(MatchError::NotInMatch);
+        }
+        if self.votes.contains_key(&player_id) {
+            return Err(MatchError::AlreadyVoted);
+        }
+        let submission = self.submissions.get(submission_id).ok_or(MatchError::UnknownSubmission(submission_id))?;
+        if submission.player_id == player_id {
+            return Err(MatchError::CannotVoteForYourself);
+        }
+
+        self.votes.insert(player_id, submission_id);
+        let vote_cast_message = ServerMessage::VoteCast {
+            player_id,
+        };
+        self.send_to_all_players(vote_cast_message);
+
+        Ok(())
+    }
+
+    // Bots vote for the submission their strategy likes best, leaving their own out
+    fn cast_bot_votes(&mut self) {
+        let black_card = match &self.black_card {
+            Some(black_card) => black_card.clone(),
+            None => return,
+        };
+        let bots: Vec<(PlayerId, Arc<dyn BotStrategy>)> = self.players.iter()
+            .filter(|pim| !self.votes.contains_key(&pim.player.id))
+            .filter_map(|pim| pim.bot.clone().map(|strategy| (pim.player.id, strategy)))
+            .collect();
+        for (bot_id, strategy) in bots {
+            let candidates: Vec<SubmissionId> = (0..self.submissions.len()).filter(|submission_id| self.submissions[*submission_id].player_id != bot_id).collect();
+            let candidate_cards: Vec<Vec<Card>> = candidates.iter().map(|submission_id| self.submissions[*submission_id].cards.clone()).collect();
+            // Only the own submission is left, so the bot has nobody to vote for
+            if let Some(choice) = strategy.choose_winner(&black_card, &candidate_cards) {
+                let _ = self.vote(bot_id, candidates[choice]);
+            }
+        }
+    }
+
+    // Whether every player who has someone else's submission to vote for did so
+    fn has_everyone_voted(&self) -> bool {
+        self.players.iter().all(|pim| {
+            self.votes.contains_key(&pim.player.id) || self.submissions.iter().all(|submission| submission.player_id == pim.player.id)
+        })
+    }
+
+    // Count the votes and let everyone see them, the submissions with the most votes win after the tiebreak
+    fn tally_votes(&mut self) -> Vec<SubmissionId> {
+        let mut vote_counts = vec![0u32; self.submissions.len()];
+        for submission_id in self.votes.values() {
+            vote_counts[*submission_id] += 1;
+        }
+        let votes_tallied_message = ServerMessage::VotesTallied {
+            votes: vote_counts.clone(),
+        };
+        self.send_to_all_players(votes_tallied_message);
+
+        let most_votes = vote_counts.iter().copied().max().unwrap_or(0);
+        if most_votes == 0 {
+            return Vec::new();
+        }
+        let tied: Vec<SubmissionId> = (0..vote_counts.len()).filter(|submission_id| vote_counts[*submission_id] == most_votes).collect();
+        match self.settings.house_rules.vote_tiebreak {
+            _ if tied.len() == 1 => tied,
+            VoteTiebreak::Random => tied.choose(&mut thread_rng()).copied().into_iter().collect(),
+            VoteTiebreak::AllTied => tied,
+            VoteTiebreak::Nobody => Vec::new(),
+        }
+    }
+
+    // Let everyone know who won and stop the match, the host can start a rematch from here
+    fn end_game(&mut self, winner: PlayerId) {
+        let game_over_message = ServerMessage::GameOver {
+            winner,
+            winning_team: self.team_of(winner),
+            scoreboard: self.scoreboard(),
+        };
+        self.send_to_all_players(game_over_message);
+        self.set_phase(MatchPhase::GameOver);
+        // Seats freed up during the game go to the waitlist now, so they count for the rematch
+        self.promote_from_waitlist();
+    }
+
+    // Move on to revealing once every player submitted
+    fn end_submitting_if_done(&mut self) {
+        if self.phase == MatchPhase::Submitting && self.has_everyone_submitted_card() {
+            self.end_submitting_or_skip_round();
+        }
+    }
+
+    fn has_any_submission(&self) -> bool {
+        self.players.iter().any(|pim| self.plays_this_round(pim.player.id) && !pim.submitted_cards.is_empty())
+    }
+
+    // Judge whatever got submitted, with nothing to judge the round is skipped
+    fn end_submitting_or_skip_round(&mut self) {
+        if self.has_any_submission() {
+            self.end_submitting();
+        } else {
+            self.finish_round();
+        }
+    }
+
+    // The submissions get shuffled so nobody knows who played what
+    fn end_submitting(&mut self) {
+        self.shuffle_submissions();
+        let submission_ids: Vec<SubmissionId> = (0..self.submissions.len()).collect();
+        let everyone_submitted_message = ServerMessage::EveryoneSubmitted {
+            submission_ids,
+            pick: self.pick_count(),
+        };
+        self.send_to_all_players(everyone_submitted_message);
+        self.set_phase(MatchPhase::Revealing);
+
+        // Without a czar everything is revealed straight away, so everyone can start voting
+        if self.settings.house_rules.god_is_dead {
+            for submission_id in 0..self.submissions.len() {
+                let _ = self.reveal_submission(submission_id);
+            }
+        }
+    }
+
+    fn has_everyone_submitted_card(&self) -> bool {
+        // With teams one submission per team is enough
+        for player in &self.players {
+            if self.plays_this_round(player.player.id) && !self.has_submitted(player.player.id) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Shuffle new draw piles out of all cards in the active decks, this forgets every card that was dealt before
+    fn create_piles(&mut self, card_cache: &CardDeckCache) {
+        let (mut black_cards, mut white_cards) = card_cache.get_cards(&self.settings.active_decks);
+        // Cards from before picks were checked might ask for more cards than anyone holds, nobody could submit for those
+        let hand_size = self.settings.hand_size;
+        black_cards.retain(|card| card.pick <= hand_size.min(MAX_PICK));
+        white_cards.extend((0..self.settings.blank_cards as CardId).map(|i| Card{id: FIRST_BLANK_CARD_ID - i, content: String::new(), pick: 1}));
+        self.black_pile = CardPile::new(black_cards);
+        self.white_pile = CardPile::new(white_cards);
+    }
+
+    // Take the played cards out of everyone's hand, pass the czar on and deal the next round
+    fn finish_round(&mut self) {
+        let czar = self.czar;
+        for player_index in 0..self.players.len() {
+            let player_in_match = &mut self.players[player_index];
+            let player_id = player_in_match.player.id;
+            // With God Is Dead there is no czar, so everyone played
+            if czar == PlayerNilId || player_id != czar {
+                for submitted_card in std::mem::take(&mut player_in_match.submitted_cards) {
+                    let player_in_match = &mut self.players[player_index];
+                    if let Some(card_pos) = player_in_match.cards.iter().position(|card| card.id == submitted_card.id) {
+                        let played_card = player_in_match.cards.remove(card_pos);
+                        let remove_card_message = ServerMessage::RemoveCard {
+                            card_id: played_card.id,
+                        };
+                        self.send_to_player(player_id, remove_card_message);
+                        self.white_pile.discard(played_card);
+                    }
+                }
+            }
+
+            self.players[player_index].submitted_cards.clear();
+        }
+        self.submissions.clear();
+        self.eliminating_player = PlayerNilId;
+        self.votes.clear();
+        // Between rounds is the only moment a seat can be given to someone waiting
+        self.promote_from_waitlist();
+        self.pass_czar();
+
+        let new_round_message = ServerMessage::NewRound;
+        self.send_to_all_players(new_round_message);
+
+        self.start_round();
+    }
+
+    // Play random cards from the hand of everyone who didn't submit in time, players without enough cards are skipped
+    fn submit_random_cards(&mut self) {
+        let pick_count = self.pick_count() as usize;
+        for player_index in 0..self.players.len() {
+            let player_in_match = &self.players[player_index];
+            // Nobody wrote anything on the blank cards, so they are left out
+            let playable_cards: Vec<Card> = player_in_match.cards.iter().filter(|card| !card.is_blank()).cloned().collect();
+            if !self.plays_this_round(player_in_match.player.id) || self.has_submitted(player_in_match.player.id) || playable_cards.len() < pick_count {
+                continue;
+            }
+            let player_in_match = &mut self.players[player_index];
+
+            player_in_match.submitted_cards = playable_cards.choose_multiple(&mut thread_rng(), pick_count).cloned().collect();
+            let card_ids: Vec<CardId> = player_in_match.submitted_cards.iter().map(|card| card.id).collect();
+            println!("player: {} ran out of time, submitted the random cards(ids: {:?})", player_in_match.player.id, card_ids);
+
+            let player_id = player_in_match.player.id;
+            let submit_accepted_message = ServerMessage::SubmitAccepted {
+                card_ids,
+            };
+            self.send_to_player(player_id, submit_accepted_message);
+            self.send_team_submitted(player_id);
+        }
+    }
+
+    // Make the next player the czar and let everyone know
+    fn pass_czar(&mut self) {
+        let czar_index_opt = self.players.iter().position(|pim| pim.player.id == self.czar);
+        match czar_index_opt {
+            // With teams the czar goes to the next team, and within that team to the next player
+            _ if self.settings.teams != 0 => {
+                self.czar = self.next_team_czar();
+            },
+            Some(czar_index) => {
+                let new_czar_index = increment_and_wrap(czar_index, self.players.len(), 0);
+                self.czar = self.next_eligible_czar(new_czar_index);
+            },
+            None => { 
+                // Right now, if it can't find the last czar, it will default to p1, however thats unfair for the last player.
+                // However, this should "never" happen as when the player disconnects it already gets handled and czar gets handed over.
+                // Which makes this soludion here solid.
+                self.czar = self.next_eligible_czar(0);
+            }
+        }
+
+        let new_czar_message = ServerMessage::NewCzar {
+            czar: self.czar,
+        };
+        self.send_to_all_players(new_czar_message);
+    }
+
+    // The next player in line to be czar in the team after the team of the current czar, teams without anyone who can be czar are skipped
+    fn next_team_czar(&mut self) -> PlayerId {
+        let team_count = self.settings.teams as TeamId;
+        let czar_team = self.team_of(self.czar).unwrap_or(team_count - 1);
+        for offset in 1..=team_count {
+            let team = (czar_team + offset) % team_count;
+            let candidates: Vec<PlayerId> = self.team_members(team).filter(|pim| !pim.is_rando()).map(|pim| pim.player.id).collect();
+            if candidates.is_empty() {
+                continue;
+            }
+            let next_index = candidates.iter().position(|player_id| *player_id == self.last_team_czars[team]).map_or(0, |index| (index + 1) % candidates.len());
+            self.last_team_czars[team] = candidates[next_index];
 
+            return candidates[next_index];
+        }
 
+        PlayerNilId
+    }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
-pub struct CardDeck {
-    pub deck_name: String,
-    pub black_cards: Vec<Card>,
-    pub white_cards: Vec<Card>,
-}
+    // Deal fresh piles and start the first round
+    fn start(&mut self, card_cache: &CardDeckCache) {
+        self.create_piles(card_cache);
+        // With teams the first czar comes from the first team, and the rotation within every team starts over
+        if self.settings.teams != 0 {
+            self.last_team_czars = vec![PlayerNilId; self.settings.teams as usize];
+            self.czar = PlayerNilId;
+            self.pass_czar();
+        }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
-pub struct Player {
-    pub name: String,
-    pub id: PlayerId,
-}
+        let match_started_message = ServerMessage::MatchStarted;
+        self.send_to_all_players(match_started_message);
 
-#[derive(PartialEq, Eq)]
-pub enum MatchInProgress {
-    NotStarted,
-    InProgress,
-}
-impl Default for MatchInProgress {
-    fn default() -> Self{
-        MatchInProgress::NotStarted
+        self.start_round();
     }
-}
 
-#[derive(Default, Clone)]
-pub struct PlayerInMatch {
-    player: Player,
-    cards: Vec<Card>,
-    points: u32,
-    submitted_card: Option<Card>,
-    socket_actor: Option<Addr<crate::MyWebSocket>>,
-}
+    // Forget the points, hands and cards of the last game, the players stay in the match
+    fn reset_for_rematch(&mut self) {
+        for player_in_match in &mut self.players {
+            player_in_match.points = 0;
+            player_in_match.cards.clear();
+            player_in_match.submitted_cards.clear();
+        }
+        self.submissions.clear();
+        self.black_card = None;
+        self.final_round = false;
+        self.eliminating_player = PlayerNilId;
+        self.votes.clear();
+        self.team_points = vec![0; self.settings.teams as usize];
+
+        let rematch_message = ServerMessage::Rematch;
+        self.send_to_all_players(rematch_message);
+    }
 
-// Increment by one (unchecked) and then wrap it to `wrap_to` if the new value is equal to `wrap_from`
-//
-// @arg value is the value being wrapped if it's to big
-// @arg wrap_from if the `value`  reaches this number (inclusive) then the returned number will be equal to `wrap_to`
-// @arg wrap_to the value being wrapped to once `value` reaches `wrap_from`
-fn increment_and_wrap<T: PrimInt>(value: T, wrap_from: T, wrap_to: T) -> T {
-    assert!(wrap_from > wrap_to);
+    // Everything a player needs to know to show the match as it is right now, None if the player isn't in this match or on its waitlist
+    fn game_state(&self, player_id: PlayerId) -> Option<GameState> {
+        if let Some(waitlist_position) = self.waitlist_position(player_id) {
+            return Some(GameState{
+                our_player: self.waitlist[waitlist_position - 1].player.clone(),
+                spectating: false,
+                waitlist_position: Some(waitlist_position),
+                ..self.spectator_game_state()
+            });
+        }
 
-    let new_val = value + T::one();
-    if new_val >= wrap_from {
-        wrap_to
-    } else {
-        new_val
+        let player_in_match = self.players.iter().find(|pim| pim.player.id == player_id)?;
+
+        Some(GameState{
+            our_player: player_in_match.player.clone(),
+            hand_of_cards: player_in_match.cards.clone(),
+            submitted_cards: player_in_match.submitted_cards.clone(),
+            spectating: false,
+            invite_code: self.access.invite_code(),
+            team_hands: player_in_match.team.map(|team| self.team_hands(team)).unwrap_or_default(),
+            ..self.spectator_game_state()
+        })
     }
-}
 
-pub struct Match {
-    players: Vec<PlayerInMatch>,
-    match_progress: MatchInProgress,
-    active_decks: Vec<String>,
-    czar: PlayerId,
-    points_to_win: u32,
-}
-impl Default for Match{
-    fn default() -> Self {
-        Match {
-            players: Default::default(),
-            match_progress: Default::default(),
-            active_decks: Default::default(),
-            czar: PlayerNilId,
-            points_to_win: 7,
+    // The match as everyone can see it, without any hand
+    fn spectator_game_state(&self) -> GameState {
+        GameState{
+            other_players: self.players.iter().map(|pim| pim.player.clone()).collect(),
+            our_player: Player{name: String::new(), id: PlayerNilId},
+            hand_of_cards: Vec::new(),
+            czar: self.czar,
+            host: self.host,
+            started: self.phase != MatchPhase::Lobby,
+            locked: self.locked,
+            settings: self.settings.clone(),
+            phase: self.phase,
+            deadline: self.deadline,
+            black_card: self.black_card.clone(),
+            submitted_cards: Vec::new(),
+            scores: self.scoreboard(),
+            submissions: self.submissions.iter().enumerate()
+                .map(|(submission_id, submission)| SubmissionState{
+                    submission_id,
+                    cards: if submission.revealed { Some(submission.cards.clone()) } else { None },
+                    eliminated: submission.eliminated,
+                })
+                .collect(),
+            spectating: true,
+            invite_code: None,
+            waitlist_position: None,
+            final_round: self.final_round,
+            eliminating_player: self.eliminating_player,
+            voted_players: self.votes.keys().copied().collect(),
+            teams: (0..self.team_points.len())
+                .map(|team| TeamState{
+                    team,
+                    players: self.team_members(team).map(|pim| pim.player.id).collect(),
+                    points: self.team_points[team],
+                })
+                .collect(),
+            team_hands: Vec::new(),
+            chat: self.chat_history.iter().cloned().collect(),
+            seq: self.last_seq(),
         }
     }
-}
-impl Match {
-    fn remove_player(&mut self, user_id: &PlayerId) -> Option<PlayerInMatch>{
-        let player_pos_option = self.players.iter().position(move |player| player.player.id == *user_id);
-        match player_pos_option {
-            Some(player_pos) => {
-                if self.players.len() > 1 {
-                    let next_czar_pos = increment_and_wrap(player_pos+1, self.players.len(), 0);
-                    self.czar = self.players.get(next_czar_pos).expect("My increment_and_wrap function is giving me our of range player indices!").player.id;
-                } else {
-                    self.czar = PlayerNilId;
-                }
-                let player = self.players.remove(player_pos);
 
-                Some(player)
+    // Every player with their points, the highest score first
+    fn scoreboard(&self) -> Vec<Score> {
+        let mut scoreboard: Vec<Score> = self.players.iter()
+            .map(|pim| Score{player: pim.player.clone(), points: pim.points})
+            .collect();
+        scoreboard.sort_by_key(|score| std::cmp::Reverse(score.points));
+
+        scoreboard
+    }
+
+    // Discard the current black card and let everyone know about the next one
+    fn next_black_card(&mut self) {
+        if let Some(old_black_card) = self.black_card.take() {
+            if old_black_card.id != HAIKU_CARD_ID {
+                self.black_pile.discard(old_black_card);
+            }
+        }
+
+        let next_black_card = if self.final_round {
+            Some(Card{id: HAIKU_CARD_ID, content: str!("Make a haiku."), pick: 3})
+        } else {
+            self.black_pile.draw()
+        };
+        match next_black_card {
+            Some(card) => {
+                let new_black_message = ServerMessage::NewBlack {
+                    card_id: card.id,
+                    card_content: card.content.clone(),
+                    pick: card.pick,
+                };
+                self.black_card = Some(card);
+                self.send_to_all_players(new_black_message);
             },
-            None => { None }
+            None => println!("ERROR: There are no black cards in the active decks: {:?}", self.settings.active_decks),
         }
     }
 
-    fn has_everyone_submitted_card(&self) -> bool {
-        for player in &self.players {
-            if player.player.id != self.czar && player.submitted_card.is_none() {
-                return false;
+    // Draw white cards for every player until they hold `MatchSettings::hand_size` cards again
+    fn refill_hands(&mut self) {
+        for player_index in 0..self.players.len() {
+            self.refill_hand(player_index);
+        }
+    }
+
+    fn refill_hand(&mut self, player_index: usize) {
+        self.refill_hand_to(player_index, self.settings.hand_size as usize);
+    }
+
+    fn refill_hand_to(&mut self, player_index: usize, hand_size: usize) {
+        let player_id = self.players[player_index].player.id;
+        while self.players[player_index].cards.len() < hand_size {
+            let card = match self.white_pile.draw() {
+                Some(card) => card,
+                None => {
+                    println!("ERROR: There are not enough white cards in the active decks: {:?} to fill everyone's hand", self.settings.active_decks);
+                    break;
+                }
+            };
+
+            let add_card_to_hand_message = ServerMessage::AddCardToHand {
+                card_id: card.id,
+                card_content: card.content.clone(),
+            };
+            self.send_to_player(player_id, add_card_to_hand_message);
+
+            self.players[player_index].cards.push(card);
+        }
+    }
+
+    // The amount of white cards every player has to submit for the current black card
+    fn pick_count(&self) -> u32 {
+        match &self.black_card {
+            Some(black_card) => black_card.pick.max(1),
+            None => 1,
+        }
+    }
+
+    // Collect everyone's submitted cards in a random order, so the index can be send to clients without revealing who played what.
+    fn shuffle_submissions(&mut self) {
+        let mut submissions: Vec<Submission> = self.players.iter()
+            .filter(|player| self.plays_this_round(player.player.id) && !player.submitted_cards.is_empty())
+            .map(|player| Submission{player_id: player.player.id, cards: player.submitted_cards.clone(), revealed: false, eliminated: false})
+            .collect();
+        submissions.shuffle(&mut thread_rng());
+
+        self.submissions = submissions;
+    }
+
+    // Send a public message to every player, spectator and everyone on the waitlist
+    fn send_to_all_players(&mut self, msg: ServerMessage) {
+        self.send_event(Audience::Everyone, msg);
+    }
+
+    // Send a message only one player gets to see, like the cards in their hand
+    fn send_to_player(&mut self, player_id: PlayerId, msg: ServerMessage) {
+        self.send_event(Audience::Players(vec![player_id]), msg);
+    }
+
+    fn send_to_team(&mut self, team: TeamId, msg: ServerMessage) {
+        let team_member_ids = self.team_members(team).map(|pim| pim.player.id).collect();
+        self.send_event(Audience::Players(team_member_ids), msg);
+    }
+
+    // Number the event, keep it for replays and send it to everyone in the audience who is connected
+    fn send_event(&mut self, audience: Audience, msg: ServerMessage) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let message = messages::outgoing::Message::sequenced(seq, &msg);
+
+        // The waitlist watches along until they get a seat
+        for player in self.players.iter().chain(self.waitlist.iter()) {
+            if let Some(socket_actor) = &player.socket_actor {
+                if audience.includes(player.player.id) {
+                    socket_actor.do_send(message.clone());
+                }
+            }
+        }
+        if audience == Audience::Everyone {
+            for spectator in &self.spectators {
+                spectator.do_send(message.clone());
             }
         }
 
-        true
+        if self.replay_buffer.len() == REPLAY_BUFFER_SIZE {
+            self.replay_buffer.pop_front();
+        }
+        self.replay_buffer.push_back(BufferedEvent{seq, audience, message});
     }
 
-    fn send_to_all_players(&mut self, msg: messages::outgoing::Message) {
-        for player in &self.players {
-            match &player.socket_actor{
-                Some(socket_actor) => {
-                    socket_actor.do_send(msg.clone());
-                },
-                None => {},
+    // The seq of the latest event, game states are up to date until there
+    fn last_seq(&self) -> u64 {
+        self.next_seq - 1
+    }
+
+    // Send a reconnecting socket every event after `last_seq` it would have received, None for spectators.
+    // @return false if some of them aren't in the replay buffer anymore, the socket needs a new game state then
+    fn replay_events(&self, addr: &Addr<crate::MyWebSocket>, player_id: Option<PlayerId>, last_seq: u64) -> bool {
+        let oldest_seq = self.replay_buffer.front().map(|event| event.seq).unwrap_or(self.next_seq);
+        // Seqs ahead of ours are from another match which had the same name
+        if last_seq + 1 < oldest_seq || last_seq > self.last_seq() {
+            return false;
+        }
+
+        for event in self.replay_buffer.iter().filter(|event| event.seq > last_seq) {
+            let is_for_us = match player_id {
+                Some(player_id) => event.audience.includes(player_id),
+                None => event.audience == Audience::Everyone,
+            };
+            if is_for_us {
+                addr.do_send(event.message.clone());
             }
         }
+        true
     }
 }
 
@@ -188,14 +1894,102 @@ impl Match {
 // }
 
 
-/// struct used for sending over network, for syncing new clients
+/// struct used for sending over network, for syncing new and reconnecting clients
 #[derive(Serialize, Deserialize)]
 pub struct GameState {
     other_players: Vec<Player>,
     our_player: Player,
     hand_of_cards: Vec<Card>,
     czar: PlayerId,
+    host: PlayerId,
     started: bool,
+    locked: bool,
+    settings: MatchSettings,
+    phase: MatchPhase,
+    // See `Match::deadline`
+    deadline: Option<u64>,
+    black_card: Option<Card>,
+    // The cards we played this round, empty if we didn't submit yet
+    submitted_cards: Vec<Card>,
+    scores: Vec<Score>,
+    // Empty until everyone submitted their cards
+    submissions: Vec<SubmissionState>,
+    // Spectators only watch, `our_player` is empty for them
+    spectating: bool,
+    // So players can invite others to an invite only match, None otherwise
+    invite_code: Option<String>,
+    // Our place on the waitlist, 1 is next in line. None once we are a player
+    waitlist_position: Option<usize>,
+    // See `Match::final_round`
+    final_round: bool,
+    // See `Match::eliminating_player`
+    eliminating_player: PlayerId,
+    // The players who already voted with the God Is Dead house rule, but not what they voted for
+    voted_players: Vec<PlayerId>,
+    // Empty when the match is played without teams
+    teams: Vec<TeamState>,
+    // The hands of everyone in our team, including our own
+    team_hands: Vec<TeamHand>,
+    // The latest messages of the match chat, oldest first
+    chat: Vec<ChatEntry>,
+    // The seq of the latest event this state includes, the events after it follow
+    seq: u64,
+}
+
+/// struct used for sending over network, who is in a team and how many points it has
+#[derive(Serialize, Deserialize)]
+pub struct TeamState {
+    team: TeamId,
+    players: Vec<PlayerId>,
+    points: u32,
+}
+
+/// struct used for sending over network, the hand of a team member
+#[derive(Serialize, Deserialize)]
+pub struct TeamHand {
+    player_id: PlayerId,
+    cards: Vec<Card>,
+}
+
+/// struct used for sending over network, a submission of the current round as everyone can see it
+#[derive(Serialize, Deserialize)]
+pub struct SubmissionState {
+    submission_id: SubmissionId,
+    // None while the czar hasn't revealed it yet
+    cards: Option<Vec<Card>>,
+    eliminated: bool,
+}
+
+/// struct used for sending over network, one message of the match chat
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChatEntry {
+    // None for messages of the server itself
+    player_id: Option<PlayerId>,
+    text: String,
+    // In milliseconds since the unix epoch
+    sent_at: u64,
+}
+
+/// struct used for sending over network, the points of one player
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Score {
+    pub player: Player,
+    pub points: u32,
+}
+
+/// struct used for sending over network, describing a match in the match list
+#[derive(Serialize, Deserialize)]
+pub struct MatchInfo {
+    pub name: String,
+    pub host: PlayerId,
+    pub player_count: u32,
+    pub spectator_count: u32,
+    pub waitlist_count: u32,
+    pub started: bool,
+    pub locked: bool,
+    // A password is needed to join, invite only matches are only listed for their players
+    pub private: bool,
+    pub settings: MatchSettings,
 }
 
 pub struct WithCounter<T: Clone> {
@@ -239,16 +2033,13 @@ pub struct DeckCardIds {
 #[derive(Default)]
 pub struct CardDeckCache{
     // All the cards in use at the moment
-    cards: HashMap<CardId, String>,
+    cards: HashMap<CardId, Card>,
     // Decks name to card id vector
     decks: HashMap<String, WithCounter<DeckCardIds> >,
 }
 impl CardDeckCache {
     pub fn get_card(&self, card_id: CardId) -> Option<Card> {
-        match self.cards.get(&card_id) {
-            Some(card_content) => Some(Card{content: card_content.to_string(), id: card_id}),
-            None => None,
-        }
+        self.cards.get(&card_id).cloned()
     }
 
     pub fn add_deck(&mut self, deck: &CardDeck) {
@@ -258,14 +2049,14 @@ impl CardDeckCache {
             Entry::Vacant(vacant_entry) => {
                 let mut card_ids = DeckCardIds{black_cards: Vec::with_capacity(deck.black_cards.len()), white_cards: Vec::with_capacity(deck.white_cards.len())};
                 for card in &deck.black_cards {
-                    card_ids.black_cards.push(card.id.clone());
-                    let old_val_opt = self.cards.insert(card.id.clone(), card.content.clone());
+                    card_ids.black_cards.push(card.id);
+                    let old_val_opt = self.cards.insert(card.id, card.clone());
                     debug_assert!(old_val_opt.is_none(), 
                         "We should never override a pair here because the card_id should be unique. And we ref count our loaded decks.");
                 }
                 for card in &deck.white_cards {
-                    card_ids.white_cards.push(card.id.clone());
-                    let old_val_opt = self.cards.insert(card.id.clone(), card.content.clone());
+                    card_ids.white_cards.push(card.id);
+                    let old_val_opt = self.cards.insert(card.id, card.clone());
                     debug_assert!(old_val_opt.is_none(), 
                         "We should never override a pair here because the card_id should be unique. And we ref count our loaded decks.");
                 }
@@ -275,8 +2066,8 @@ impl CardDeckCache {
 
     }
 
-    pub fn remove_deck(&mut self, deck: &CardDeck) {
-        let deck_entry = self.decks.entry(deck.deck_name.clone());
+    pub fn remove_deck(&mut self, deck_name: &str) {
+        let deck_entry = self.decks.entry(deck_name.to_string());
         match deck_entry {
             Entry::Occupied(mut occupied_entry) => {
                 let should_be_removed = occupied_entry.get_mut().decrement_counter();
@@ -297,126 +2088,417 @@ impl CardDeckCache {
         }
     }
 
-    pub fn get_random_black_card(&self, active_decks: &Vec<String>) -> Option<Card> {
-        let mut weights = Vec::new();
+    // Every black and white card of the given decks, decks which aren't loaded in the cache are skipped.
+    // Returns: (black_cards, white_cards)
+    pub fn get_cards(&self, active_decks: &[String]) -> (Vec<Card>, Vec<Card>) {
+        let mut black_cards = Vec::new();
+        let mut white_cards = Vec::new();
         for deck_name in active_decks {
-            if let Some(card_deck_rc) = self.decks.get(deck_name) {
-                let card_deck = &card_deck_rc.value;
-                weights.push(card_deck.black_cards.len());
+            if let Some(deck_ids) = self.decks.get(deck_name) {
+                black_cards.extend(deck_ids.value.black_cards.iter().filter_map(|card_id| self.cards.get(card_id).cloned()));
+                white_cards.extend(deck_ids.value.white_cards.iter().filter_map(|card_id| self.cards.get(card_id).cloned()));
+            } else {
+                println!("ERROR: The deck '{}' is not loaded in the card cache, so its cards can't be used!", deck_name);
             }
         }
 
-        let mut rng = thread_rng();
-        let distribution_result = WeightedIndex::new(weights.as_slice());
-        let deck_index = match distribution_result {
-            Ok(distribution) => {
-                distribution.sample(&mut rng)
-            },
-            Err(weighted_err) => { 
-                debug_assert!(false); 
-                println!("ERROR: Could not add a card to the hand!! {}", weighted_err);
-                return None;
-            }
-        };
+        (black_cards, white_cards)
+    }
+}
+
+/// `CahServer`(Cards against humanity server) manages matches and responsible for coordinating
+/// session. 
+pub struct CahServer {
+    //socket_actors: HashMap<CookieToken, Addr<crate::MyWebSocket>>,
+    // The cookie token to player PlayerId, the PlayerId is the reprisentation internally.
+    sessions: RwLock<HashMap<CookieToken, PlayerId>>, //TODO: Clear sessins after a couple hours/days, so the ram doesn't quitely go downwards.
+    matches: RwLock<HashMap<String, Match>>,
+    database: RwLock<Database>,
+    card_cache: RwLock<CardDeckCache>,
+}
+
+impl CahServer {
+    pub fn new(connection_pool: Pool) -> Self {
+        let db: Database = Database::new(connection_pool);
+
+        // Decks are loaded into the card cache by the matches using them
+        CahServer {
+            sessions: Default::default(),
+            matches: Default::default(),
+            database: RwLock::new(db),
+            card_cache: Default::default(),
+        }
+    } 
 
-        let ref picked_deck_name = active_decks[deck_index];
-        let deck_ids_opt = &self.decks.get(picked_deck_name);
-        if let Some(ref deck_ids) = deck_ids_opt {
-            let local_card_index = rng.gen_range(0, deck_ids.value.black_cards.len());
-            let local_card_id = deck_ids.value.black_cards[local_card_index];
-            let card_content_opt = self.cards.get(&local_card_id);
-            if let Some(card_content) = card_content_opt {
-                return Some(Card{content: card_content.clone(), id: local_card_id.clone()});
+    //TODO: Optimize
+    fn get_room_from_uuid(&self, user_id: &PlayerId) -> Option<String> {
+        for room in self.matches.read().unwrap().iter() {
+            for player in room.1.players.iter().chain(room.1.waitlist.iter()) {
+                if &player.player.id == user_id {
+                    return Some(room.0.clone());
+                }
             }
         }
-        
+
         None
     }
 
-    pub fn get_random_white_card(&self, active_decks: &Vec<String>) -> Option<Card> {
-        let mut weights = Vec::new();
-        for deck_name in active_decks {
-            if let Some(card_deck_rc) = self.decks.get(deck_name) {
-                let card_deck = &card_deck_rc.value;
-                weights.push(card_deck.white_cards.len());
+    // Restart the countdown of a match whenever its deadline changed, a match without deadline gets its countdown cancelled
+    fn sync_phase_timer(room: &mut Match, match_name: &str, ctx: &mut Context<Self>) {
+        // Every change to a match ends up here, so this is also where the bots get their turn
+        CahServer::schedule_bots(room, match_name, ctx);
+
+        if room.timer.map(|(_, deadline)| deadline) == room.deadline {
+            return;
+        }
+
+        if let Some((timer_handle, _)) = room.timer.take() {
+            ctx.cancel_future(timer_handle);
+        }
+        if let Some(deadline) = room.deadline {
+            let match_name = match_name.to_string();
+            let time_left = Duration::from_millis(deadline.saturating_sub(unix_time_millis()));
+            let timer_handle = ctx.run_later(time_left, move |cah, ctx| cah.phase_timed_out(&match_name, deadline, ctx));
+            room.timer = Some((timer_handle, deadline));
+        }
+    }
+
+    // Let a bot czar reveal and judge the submissions of this round once per round, let a bot eliminate a submission when it is its turn and let the bots vote
+    fn schedule_bots(room: &mut Match, match_name: &str, ctx: &mut Context<Self>) {
+        let is_bot = |player_id: PlayerId| room.players.iter().any(|pim| pim.player.id == player_id && pim.is_bot());
+
+        if room.phase == MatchPhase::Judging && room.settings.house_rules.god_is_dead {
+            // Voting twice is refused, so scheduling this more than once per round is harmless
+            if let Some(deadline) = room.deadline {
+                let match_name = match_name.to_string();
+                ctx.run_later(BOT_VOTE_DELAY, move |cah, ctx| cah.bot_vote_turn(&match_name, deadline, ctx));
             }
         }
 
-        let mut rng = thread_rng();
-        let distribution_result = WeightedIndex::new(weights.as_slice());
-        let deck_index = match distribution_result {
-            Ok(distribution) => {
-                distribution.sample(&mut rng)
+        if room.phase == MatchPhase::Judging && is_bot(room.eliminating_player) {
+            // A turn is identified by who eliminates and how many submissions are left, so scheduling it twice is harmless
+            let eliminating_player = room.eliminating_player;
+            let remaining_count = room.remaining_submissions().len();
+            let match_name = match_name.to_string();
+            ctx.run_later(BOT_ELIMINATION_DELAY, move |cah, ctx| cah.bot_elimination_turn(&match_name, eliminating_player, remaining_count, ctx));
+        }
+
+        if room.phase != MatchPhase::Revealing || !is_bot(room.czar) || room.bot_czar_deadline == room.deadline {
+            return;
+        }
+
+        if let Some(deadline) = room.deadline {
+            room.bot_czar_deadline = Some(deadline);
+            let match_name = match_name.to_string();
+            ctx.run_later(BOT_CZAR_DELAY, move |cah, ctx| cah.bot_czar_turn(&match_name, deadline, ctx));
+        }
+    }
+
+    // Bots don't judge what they throw out, they eliminate a random submission
+    fn bot_elimination_turn(&mut self, match_name: &str, eliminating_player: PlayerId, remaining_count: usize, ctx: &mut Context<Self>) {
+        let room = match self.matches.get_mut().unwrap().get_mut(match_name) {
+            Some(room) if room.phase == MatchPhase::Judging && room.eliminating_player == eliminating_player => room,
+            _ => return,
+        };
+        let remaining_submissions = room.remaining_submissions();
+        if remaining_submissions.len() != remaining_count {
+            return;
+        }
+
+        if let Some(submission_id) = remaining_submissions.choose(&mut thread_rng()) {
+            println!("room: {}. bot: {} eliminated the submission: {}", match_name, eliminating_player, submission_id);
+            let _ = room.eliminate_submission(eliminating_player, *submission_id);
+            let _ = CahServer::judge_survivor(room, match_name, ctx);
+        }
+    }
+
+    // The bots cast their votes, and the round is decided once nobody has a vote left. This also catches a round where nobody can vote at all
+    fn bot_vote_turn(&mut self, match_name: &str, deadline: u64, ctx: &mut Context<Self>) {
+        let room = match self.matches.get_mut().unwrap().get_mut(match_name) {
+            Some(room) if room.phase == MatchPhase::Judging && room.deadline == Some(deadline) => room,
+            _ => return,
+        };
+
+        room.cast_bot_votes();
+        if room.has_everyone_voted() {
+            CahServer::judge_votes(room, match_name, ctx);
+        }
+    }
+
+    // With Survival of the Fittest the last submission standing wins the round, otherwise the match keeps waiting on the players
+    fn judge_survivor(room: &mut Match, match_name: &str, ctx: &mut Context<Self>) -> Result<(), MatchError> {
+        match room.survivor() {
+            Some(survivor) => CahServer::judge_submission(room, match_name, survivor, ctx),
+            None => {
+                CahServer::sync_phase_timer(room, match_name, ctx);
+                Ok(())
+            },
+        }
+    }
+
+    // The bot czar reveals every submission at once, and picks a winner after thinking about them for a while
+    fn bot_czar_turn(&mut self, match_name: &str, deadline: u64, ctx: &mut Context<Self>) {
+        let room = match self.matches.get_mut().unwrap().get_mut(match_name) {
+            Some(room) if room.deadline == Some(deadline) => room,
+            // The round is already over, or the match is gone
+            _ => return,
+        };
+        let czar = room.czar;
+        let strategy = match room.players.iter().find(|pim| pim.player.id == czar).and_then(|pim| pim.bot.clone()) {
+            Some(strategy) => strategy,
+            // The bot got kicked and a person took over as czar
+            None => return,
+        };
+
+        match room.phase {
+            MatchPhase::Revealing => {
+                for submission_id in 0..room.submissions.len() {
+                    let _ = room.reveal_submission(submission_id);
+                }
+                let _ = CahServer::judge_survivor(room, match_name, ctx);
+                let match_name = match_name.to_string();
+                ctx.run_later(BOT_CZAR_DELAY, move |cah, ctx| cah.bot_czar_turn(&match_name, deadline, ctx));
             },
-            Err(weighted_err) => { 
-                debug_assert!(false); 
-                println!("ERROR: Could not add a card to the hand!! {}", weighted_err);
-                return None;
-            }
+            // With Survival of the Fittest the players decide
+            MatchPhase::Judging if !room.settings.house_rules.survival_of_the_fittest => {
+                let black_card = match &room.black_card {
+                    Some(black_card) => black_card.clone(),
+                    None => return,
+                };
+                let submitted_cards: Vec<Vec<Card>> = room.submissions.iter().map(|submission| submission.cards.clone()).collect();
+                let submission_id = match strategy.choose_winner(&black_card, &submitted_cards) {
+                    Some(submission_id) => submission_id,
+                    // Nothing to judge, the czar timer ends the round
+                    None => return,
+                };
+                println!("room: {}. bot czar: {} choose the submission: {}", match_name, czar, submission_id);
+                let _ = CahServer::judge_submission(room, match_name, submission_id, ctx);
+            },
+            _ => {},
+        }
+    }
+
+    // Give the point to whoever played the chosen submission, then show the results or end the game
+    fn judge_submission(room: &mut Match, match_name: &str, submission_id: SubmissionId, ctx: &mut Context<Self>) -> Result<(), MatchError> {
+        let winning_submission = room.submissions.get(submission_id).ok_or(MatchError::UnknownSubmission(submission_id))?;
+        let winning_player_id = winning_submission.player_id;
+
+        let czar_choice_message = ServerMessage::CzarChoice {
+            submission_id,
+            cards: winning_submission.cards.clone(),
+        };
+        room.send_to_all_players(czar_choice_message);
+
+        CahServer::keep_winning_write_ins(room, match_name, &[submission_id], ctx);
+        CahServer::award_round(room, match_name, &[winning_player_id], ctx);
+
+        Ok(())
+    }
+
+    // Count the votes with the God Is Dead house rule, the winners of the tiebreak get a point each
+    fn judge_votes(room: &mut Match, match_name: &str, ctx: &mut Context<Self>) {
+        let winning_submissions = room.tally_votes();
+        println!("room: {}. the votes are in, the winning submissions are: {:?}", match_name, winning_submissions);
+        let winning_player_ids: Vec<PlayerId> = winning_submissions.iter().map(|submission_id| room.submissions[*submission_id].player_id).collect();
+
+        CahServer::keep_winning_write_ins(room, match_name, &winning_submissions, ctx);
+        CahServer::award_round(room, match_name, &winning_player_ids, ctx);
+    }
+
+    // Add the write-ins of the winning submissions to the write-in deck of the match, if the host picked one
+    fn keep_winning_write_ins(room: &Match, match_name: &str, winning_submissions: &[SubmissionId], ctx: &mut Context<Self>) {
+        let deck_name = match &room.settings.write_in_deck {
+            Some(deck_name) => deck_name.clone(),
+            None => return,
         };
+        let write_ins: Vec<String> = winning_submissions.iter()
+            .flat_map(|submission_id| room.submissions[*submission_id].cards.iter())
+            .filter(|card| card.is_blank())
+            .map(|card| card.content.clone())
+            .collect();
+        if write_ins.is_empty() {
+            return;
+        }
 
-        let ref picked_deck_name = active_decks[deck_index];
-        let deck_ids_opt = &self.decks.get(picked_deck_name);
-        if let Some(ref deck_ids) = deck_ids_opt {
-            let local_card_index = rng.gen_range(0, deck_ids.value.white_cards.len());
-            let local_card_id = deck_ids.value.white_cards[local_card_index];
-            let card_content_opt = self.cards.get(&local_card_id);
-            if let Some(card_content) = card_content_opt {
-                return Some(Card{content: card_content.clone(), id: local_card_id.clone()});
+        // The database can't be reached while the match is borrowed, so the cards are added right after
+        let match_name = match_name.to_string();
+        ctx.run_later(Duration::default(), move |cah, _ctx| {
+            let database = cah.database.get_mut().unwrap();
+            for card_content in write_ins {
+                match database.execute(db::AddCard{deck_name: deck_name.clone(), card_content: card_content.clone(), is_black: false, pick: 1}).wait() {
+                    Ok(card_id) => println!("room: {}. the write-in '{}' was added to the deck: {} with id: {}", match_name, card_content, deck_name, card_id),
+                    Err(db_err) => println!("room: {}. the write-in '{}' couldn't be added to the deck: {}, {}", match_name, card_content, deck_name, db_err),
+                }
             }
+        });
+    }
+
+    // Give a point to every winner of the round, then show the results or end the game. Without any winners only the results are shown
+    fn award_round(room: &mut Match, match_name: &str, winning_player_ids: &[PlayerId], ctx: &mut Context<Self>) {
+        // The haiku round is only for the honour, the player with the most points still wins the game
+        if room.final_round {
+            let winner = room.leader();
+            room.end_game(winner);
+            CahServer::sync_phase_timer(room, match_name, ctx);
+
+            return;
         }
-        
-        None
+
+        let mut did_player_win = false;
+        for winning_player_id in winning_player_ids {
+            let victorious_player_opt = room.players.iter_mut().find(|player_in_match| player_in_match.player.id == *winning_player_id);
+            // The winner might have left the match after submitting, then nobody gets the point
+            if let Some(victorious_player) = victorious_player_opt {
+                victorious_player.points += 1;
+                // With teams the points of the team decide, the points of the player are only for the scoreboard
+                let winning_team = victorious_player.team;
+                did_player_win |= match winning_team {
+                    Some(team) => {
+                        room.team_points[team] += 1;
+                        room.team_points[team] >= room.settings.points_to_win
+                    },
+                    None => victorious_player.points >= room.settings.points_to_win,
+                };
+
+                let round_won_chat = format!("{} won the round", victorious_player.player.name);
+                let round_won_message = ServerMessage::RoundWon {
+                    player_id: *winning_player_id,
+                    team: winning_team,
+                };
+                room.send_to_all_players(round_won_message);
+                room.send_system_message(round_won_chat);
+            }
+        }
+
+        if did_player_win && room.settings.house_rules.happy_ending {
+            room.final_round = true;
+            let final_round_message = ServerMessage::FinalRound;
+            room.send_to_all_players(final_round_message);
+        } else if did_player_win {
+            // With a tie for the most votes several players can reach the points to win, the scoreboard breaks that tie
+            let winner = room.leader();
+            room.end_game(winner);
+            CahServer::sync_phase_timer(room, match_name, ctx);
+
+            return;
+        }
+
+        room.set_phase(MatchPhase::RoundResults);
+        CahServer::sync_phase_timer(room, match_name, ctx);
+
+        let match_name = match_name.to_string();
+        ctx.run_later(Duration::from_millis(3000), move |cah, ctx| {
+            let matches = cah.matches.get_mut().unwrap();
+            match matches.get_mut(&match_name) {
+                // The match could have been deleted or be in another phase by now
+                Some(room) if room.phase == MatchPhase::RoundResults => {
+                    room.finish_round();
+                    CahServer::sync_phase_timer(room, &match_name, ctx);
+                },
+                _ => {},
+            }
+        });
     }
-}
 
-/// `CahServer`(Cards against humanity server) manages matches and responsible for coordinating
-/// session. 
-pub struct CahServer {
-    //socket_actors: HashMap<CookieToken, Addr<crate::MyWebSocket>>,
-    // The cookie token to player PlayerId, the PlayerId is the reprisentation internally.
-    sessions: RwLock<HashMap<CookieToken, PlayerId>>, //TODO: Clear sessins after a couple hours/days, so the ram doesn't quitely go downwards.
-    matches: RwLock<HashMap<String, Match>>,
-    database: RwLock<Database>,
-    card_cache: RwLock<CardDeckCache>,
-}
+    // A player who didn't come back within `RECONNECT_GRACE_PERIOD` gives up their seat, so the host role and their turns move on
+    fn leave_if_not_reconnected(&mut self, match_name: &str, user_id: PlayerId, disconnected_at: Instant, ctx: &mut Context<Self>) {
+        let room = match self.matches.get_mut().unwrap().get_mut(match_name) {
+            Some(room) => room,
+            None => return,
+        };
+        // Reconnecting and losing the connection again starts a new grace period
+        if !room.players.iter().chain(room.waitlist.iter()).any(|pim| pim.player.id == user_id && pim.disconnected_at == Some(disconnected_at)) {
+            return;
+        }
 
-impl CahServer {
-    pub fn new(connection_pool: Pool) -> Self {
-        // default room
-        let mut matches = HashMap::new();
-        let mut main_match = Match::default();
-        main_match.active_decks.push(str!("Default"));
-        let mut second_room_match = Match::default();
-        second_room_match.active_decks.push(str!("Default"));
-        matches.insert("Main".to_owned(), main_match);
-        matches.insert("Second Room".to_owned(), second_room_match);
+        println!("room: {}. player: {} didn't reconnect in time", match_name, user_id);
+        if room.leave(&user_id).is_some() {
+            CahServer::sync_phase_timer(room, match_name, ctx);
+        }
+    }
+
+    // Someone took too long, so the match moves on without them
+    fn phase_timed_out(&mut self, match_name: &str, deadline: u64, ctx: &mut Context<Self>) {
+        let room = match self.matches.get_mut().unwrap().get_mut(match_name) {
+            Some(room) if room.deadline == Some(deadline) => room,
+            // The match has moved on or is gone
+            _ => return,
+        };
+        room.timer = None;
+
+        // Without anyone watching there is no point in dealing more rounds, the host can start a rematch when they are back
+        if !room.players.iter().any(|pim| pim.is_connected()) {
+            println!("room: {}. nobody is connected anymore, the game ends", match_name);
+            room.end_game(room.leader());
+            CahServer::sync_phase_timer(room, match_name, ctx);
+
+            return;
+        }
+
+        match room.phase {
+            MatchPhase::Submitting => {
+                println!("room: {}. the time to submit cards ran out", match_name);
+                room.submit_random_cards();
+                room.end_submitting_or_skip_round();
+            },
+            // Whoever didn't vote in time just doesn't get a say
+            MatchPhase::Judging if room.settings.house_rules.god_is_dead => {
+                println!("room: {}. the time to vote ran out", match_name);
+                CahServer::judge_votes(room, match_name, ctx);
+
+                return;
+            },
+            // Nobody judges with Survival of the Fittest, it is the eliminating player who took too long
+            MatchPhase::Judging if room.settings.house_rules.survival_of_the_fittest => {
+                // Everyone who could take a turn left the match, so the round ends without a winner
+                if room.eliminating_player == PlayerNilId {
+                    println!("room: {}. nobody is left to eliminate a submission", match_name);
+                    room.finish_round();
+                } else {
+                    println!("room: {}. player: {} ran out of time to eliminate a submission", match_name, room.eliminating_player);
+                    room.eliminate_for_timed_out_player();
+                    let _ = CahServer::judge_survivor(room, match_name, ctx);
 
-        let mut db: Database = Database::new(connection_pool);
-        let mut card_cache: CardDeckCache = Default::default();
+                    return;
+                }
+            },
+            MatchPhase::Revealing | MatchPhase::Judging => {
+                println!("room: {}. czar player: {} ran out of time to choose a submission", match_name, room.czar);
+                let czar_timed_out_message = ServerMessage::CzarTimedOut {
+                    czar: room.czar,
+                };
+                room.send_to_all_players(czar_timed_out_message);
+                room.finish_round();
+            },
+            _ => {},
+        }
 
-        let default_card_deck = db.execute(db::GetCardDeck{deck_name: str!("Default")}).wait().unwrap();
-        card_cache.add_deck(&default_card_deck);
+        CahServer::sync_phase_timer(room, match_name, ctx);
+    }
 
-        CahServer {
-            sessions: Default::default(),
-            matches: RwLock::new(matches),
-            database: RwLock::new(db),
-            card_cache: RwLock::new(card_cache),
+    // Load the decks from the database into the card cache, they stay loaded until `CahServer::release_decks` is called for them
+    fn load_decks(&mut self, deck_names: &[String]) -> Result<(), String> {
+        let database = self.database.get_mut().unwrap();
+        let mut card_decks = Vec::with_capacity(deck_names.len());
+        for deck_name in deck_names {
+            let card_deck = database.execute(db::GetCardDeck{deck_name: deck_name.clone()}).wait()
+                .map_err(|db_err| format!("Cannot use the deck '{}': {}", deck_name, db_err))?;
+            card_decks.push(card_deck);
         }
-    } 
 
-    //TODO: Optimize
-    fn get_room_from_uuid(&self, user_id: &PlayerId) -> Option<String> {
-        for room in self.matches.read().unwrap().iter() {
-            for player in room.1.players.iter() {
-                if &player.player.id == user_id {
-                    return Some(room.0.clone());
-                }
-            }
+        let card_cache = self.card_cache.get_mut().unwrap();
+        for card_deck in &card_decks {
+            card_cache.add_deck(card_deck);
         }
 
-        None
+        Ok(())
+    }
+
+    fn release_decks(&mut self, deck_names: &[String]) {
+        let card_cache = self.card_cache.get_mut().unwrap();
+        for deck_name in deck_names {
+            card_cache.remove_deck(deck_name);
+        }
     }
 
     fn get_user_id(&self, cookie_token: &CookieToken) -> Option<PlayerId> {
@@ -425,23 +2507,6 @@ impl CahServer {
             None => None,
         }
     }
-
-    fn add_random_card(card_cache: &CardDeckCache, active_decks: &Vec<String>, player: &mut PlayerInMatch) {
-        if let Some(card) = card_cache.get_random_white_card(active_decks) {
-
-            player.cards.push(card.clone());
-
-            if let Some(socket_actor) = player.socket_actor.clone() {
-                let json_msg = json!({
-                    "type": "addCardToHand",
-                    "card_id": card.id,
-                    "card_content": card.content.clone(),
-                });
-
-                socket_actor.do_send(messages::outgoing::Message(json_msg.to_string()));
-            }
-        }
-    }
 }
 
 /// Make actor from `CahServer`
@@ -551,7 +2616,7 @@ impl Handler<messages::incomming::Login> for CahServer {
 ///
 /// Register new session and assign unique id to this session
 impl Handler<messages::incomming::SocketConnectMatch> for CahServer {
-    type Result = Result<(), String>;
+    type Result = Result<PlayerId, String>;
 
     fn handle(&mut self, msg: messages::incomming::SocketConnectMatch, ctx: &mut Context<Self>) -> Self::Result {
         // register session with token
@@ -585,16 +2650,26 @@ impl Handler<messages::incomming::SocketConnectMatch> for CahServer {
             //TODO: MyWebSocket stores the room it should be found to, and should be checked here too.
 
             if let Some(room) = self.matches.get_mut().unwrap().get_mut(&room_name) {
-                let pim_opt = room.players.iter_mut().find(|elem| elem.player.id == user_id);
+                let pim_opt = room.players.iter_mut().chain(room.waitlist.iter_mut()).find(|elem| elem.player.id == user_id);
                 match pim_opt {
                     Some(pim) => {
-                        pim.socket_actor = Some(msg.addr);
+                        pim.socket_actor = Some(msg.addr.clone());
+                        pim.disconnected_at = None;
                     },
                     None => {},
                 }
+
+                // Anything send between joining and connecting the socket was missed, so sync the client up again.
+                // A client which was connected before only needs what it missed, unless that is too much to replay
+                let replayed = msg.last_seq.is_some_and(|last_seq| room.replay_events(&msg.addr, Some(user_id), last_seq));
+                if !replayed {
+                    if let Some(game_state) = room.game_state(user_id) {
+                        msg.addr.do_send(messages::outgoing::Message::from(ServerMessage::GameState(Box::new(game_state))));
+                    }
+                }
             }
 
-            Ok(())
+            Ok(user_id)
         } else {
             Err("Could not find a match where this user is in, is JoinMatch not send beforehand?".to_owned())
         }
@@ -607,12 +2682,17 @@ impl Handler<messages::incomming::JoinMatch> for CahServer {
 
     fn handle(&mut self, msg: messages::incomming::JoinMatch, ctx: &mut Context<Self>) -> Self::Result {
         if let Some(user_id) = self.get_user_id(&msg.token) {
-            let mut already_in_match = false;
+            let current_room_opt = self.get_room_from_uuid(&user_id);
+            let already_in_match = current_room_opt.as_ref() == Some(&msg.match_name);
+
+            // Check before leaving our current match, so a rejected join doesn't cost us our seat there
+            if let Some(room) = self.matches.get_mut().unwrap().get(&msg.match_name) {
+                room.check_can_join(&msg.match_name, user_id, already_in_match, msg.password.as_deref(), msg.invite_code.as_deref())?;
+            }
 
             //Firstly disconnect from an existing match if we are switching match.
-            if let Some(room_name) = self.get_room_from_uuid(&user_id) {
+            if let Some(room_name) = current_room_opt {
                 //ctx.address().do_send(messages::incomming::Leavematch{match_name: room, token: msg.token});
-                already_in_match = room_name == msg.match_name;
                 if !already_in_match {
                     let _ = self.handle(messages::incomming::Leavematch{match_name: room_name, token: msg.token}, ctx);
                 }
@@ -620,41 +2700,42 @@ impl Handler<messages::incomming::JoinMatch> for CahServer {
             
             let matches = self.matches.get_mut().unwrap();
             if let Some(room) = matches.get_mut(&msg.match_name) {
-                let db = self.database.get_mut().unwrap();
-                let player_option = db.execute(db::GetPlayerById{player_id: user_id}).wait();
-                debug_assert!(player_option.is_ok(), 
-                    "We managed to find ourselves with the call `CahServer::get_user_id()` but we cannot find ourselves in `self.get_player_by_id()`");
-                let player = player_option.unwrap();
-                let player_in_match = PlayerInMatch{player: player.clone(), cards: Vec::new(), points: 0, submitted_card: None, socket_actor: None };
+                // Rejoining keeps our hand, points and submitted cards, or our place on the waitlist
                 if !already_in_match {
-                    room.players.push(player_in_match.clone());
-
-                    for other_player_in_match in  &room.players{
-                        let join_json = json!({
-                            "type": "player_joined",
-                            "player": player.clone(),
-                        });
-
-                        match &other_player_in_match.socket_actor {
-                            Some(socket_actor) => socket_actor.do_send(messages::outgoing::Message(join_json.to_string())),
-                            None => {}
-                        }
-                    }                    
+                    let db = self.database.get_mut().unwrap();
+                    let player_option = db.execute(db::GetPlayerById{player_id: user_id}).wait();
+                    debug_assert!(player_option.is_ok(), 
+                        "We managed to find ourselves with the call `CahServer::get_user_id()` but we cannot find ourselves in `self.get_player_by_id()`");
+                    let player = player_option.unwrap();
+                    let player_in_match = PlayerInMatch{player: player.clone(), cards: Vec::new(), points: 0, submitted_cards: Vec::new(), socket_actor: None, disconnected_at: None, bot: None, team: None, recent_chats: VecDeque::new()};
+                    if room.is_full() {
+                        println!("room: {}. is full, player: {} is put on the waitlist", &msg.match_name, user_id);
+                        room.waitlist.push_back(player_in_match);
+
+                        return Ok(room.game_state(user_id).expect("We just put ourselves on the waitlist of this match"));
+                    }
+                    room.players.push(player_in_match);
+
+                    // Players joining a running match start with an empty hand
+                    if room.phase != MatchPhase::Lobby {
+                        let new_player_index = room.players.len() - 1;
+                        room.refill_hand(new_player_index);
+                    }
+
+                    let player_joined_message = ServerMessage::PlayerJoined {
+                        player: player.clone(),
+                    };
+                    room.send_to_all_players(player_joined_message);
+                    room.send_system_message(format!("{} joined the match", player.name));
+                    room.assign_team(room.players.len() - 1);
                 }
 
                 //handle czar memes
                 if room.czar == PlayerNilId {
-                    if !room.players.is_empty() {
-                        room.czar = room.players[0].player.id;
-                    }
+                    room.czar = room.next_eligible_czar(0);
                 }
                 
-                let game_state = GameState{
-                    other_players: room.players.iter().map(|elem| elem.player.clone()).collect(), 
-                    our_player: player.clone(), 
-                    hand_of_cards: player_in_match.cards.clone(),
-                    czar: room.czar,
-                    started: room.match_progress == MatchInProgress::InProgress};
+                let game_state = room.game_state(user_id).expect("We just made sure we are a player in this match or on its waitlist");
 
                 Ok(game_state)
             } else {
@@ -670,80 +2751,65 @@ impl Handler<messages::incomming::JoinMatch> for CahServer {
 impl Handler<messages::incomming::Leavematch> for CahServer {
     type Result = ();
 
-    fn handle(&mut self, msg: messages::incomming::Leavematch, _: &mut Context<Self>) {
+    fn handle(&mut self, msg: messages::incomming::Leavematch, ctx: &mut Context<Self>) {
         if let Some(user_id) = self.get_user_id(&msg.token) {
-            match self.matches.get_mut().unwrap().get_mut(&msg.match_name) {
-                Some(room) => {
-                    let removed_player_opt = room.remove_player(&user_id);
-                    match removed_player_opt {
-                        Some(removed_player) => {
-                            for player in room.players.iter() {
-                                match &player.socket_actor {
-                                    Some(socket) => {
-                                        let leave_json = json!({
-                                            "type": "player_left",
-                                            "player_id": removed_player.player.id,
-                                        });
-                                        
-                                        socket.do_send(messages::outgoing::Message(leave_json.to_string()));
-                                    },
-                                    None => {
-                                        println!("Coudln't send the thing leave message!");
-                                    },
-                                }
-                            }
-                        },
-                        None => {},
-                    }
-                },
-                None => {},
+            if let Some(room) = self.matches.get_mut().unwrap().get_mut(&msg.match_name) {
+                if room.leave(&user_id).is_some() {
+                    CahServer::sync_phase_timer(room, &msg.match_name, ctx);
+                }
             }
         }
     }
 }
 
-impl Handler<messages::incomming::StartMatch> for CahServer {
+impl Handler<messages::incomming::SpectateMatch> for CahServer {
+    type Result = Result<(), String>;
+
+    fn handle(&mut self, msg: messages::incomming::SpectateMatch, _: &mut Context<Self>) -> Self::Result {
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name)
+            .ok_or_else(|| format!("Cannot find the room named '{}'. Has it been removed in the meantime?", msg.match_name))?;
+        if !room.access.is_public() {
+            return Err(format!("The match '{}' is private, so it can't be watched", msg.match_name));
+        }
+
+        let replayed = msg.last_seq.is_some_and(|last_seq| room.replay_events(&msg.addr, None, last_seq));
+        if !replayed {
+            msg.addr.do_send(messages::outgoing::Message::from(ServerMessage::GameState(Box::new(room.spectator_game_state()))));
+        }
+
+        println!("room: {}. a spectator started watching", &msg.match_name);
+        room.spectators.push(msg.addr);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::StopSpectating> for CahServer {
     type Result = ();
 
-    fn handle(&mut self, msg: messages::incomming::StartMatch, ctx: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: messages::incomming::StopSpectating, _: &mut Context<Self>) {
+        if let Some(room) = self.matches.get_mut().unwrap().get_mut(&msg.match_name) {
+            room.spectators.retain(|spectator| *spectator != msg.addr);
+        }
+    }
+}
+
+impl Handler<messages::incomming::SocketDisconnect> for CahServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: messages::incomming::SocketDisconnect, ctx: &mut Context<Self>) {
         if let Some(user_id) = self.get_user_id(&msg.token) {
             if let Some(room) = self.matches.get_mut().unwrap().get_mut(&msg.match_name) {
-                if room.players.len() >= 3 {
-                    if let Some(player_in_match) = room.players.iter().find(|elem| elem.player.id == user_id) {
-                        // We found ourselves so there should be at least 1 entry
-                        // Check if we are the czar:
-                        if room.players[0].player.id == player_in_match.player.id && room.match_progress == MatchInProgress::NotStarted {
-                            room.match_progress = MatchInProgress::InProgress;
-                            
-                            let db = self.database.get_mut().unwrap();
-                            let default_card_deck = db.execute(db::GetCardDeck{deck_name: str!("Default")}).wait().unwrap();//db.card_decks.get("Default").unwrap();
-                            let msg_json = json!({
-                                "type": "matchStarted",
-                            });
-                            for every_player in &mut room.players {
-                                match &every_player.socket_actor{
-                                    Some(socket_actor) => {
-                                        socket_actor.do_send(messages::outgoing::Message( msg_json.to_string() ));
-
-                                        let random_cards: Vec<_> = default_card_deck.white_cards.choose_multiple(&mut rand::thread_rng(), 3).collect();
-                                        for card in random_cards{
-                                            every_player.cards.push(card.clone());
-                                            ctx.address().do_send(messages::outgoing::AddCardToHand{room: msg.match_name.clone(), player: every_player.player.clone(), card: card.clone()});
-                                        }
-                                    },
-                                    None => {}
-                                }
-                            }
-
-                            if let Some(card) = self.card_cache.read().unwrap().get_random_black_card(&room.active_decks) {
-                                let json_msg = json!({
-                                    "type": "newBlack",
-                                    "card_id": card.id,
-                                    "card_content": card.content
-                                });
-                                room.send_to_all_players(messages::outgoing::Message(json_msg.to_string()))
-                            }
-                        }
+                if let Some(pim) = room.players.iter_mut().chain(room.waitlist.iter_mut()).find(|pim| pim.player.id == user_id) {
+                    // A refreshed page might have connected its new socket already
+                    if pim.socket_actor.as_ref() == Some(&msg.addr) {
+                        println!("room: {}. player: {} lost their connection", &msg.match_name, user_id);
+                        let disconnected_at = Instant::now();
+                        pim.socket_actor = None;
+                        pim.disconnected_at = Some(disconnected_at);
+
+                        let match_name = msg.match_name.clone();
+                        ctx.run_later(RECONNECT_GRACE_PERIOD, move |cah, ctx| cah.leave_if_not_reconnected(&match_name, user_id, disconnected_at, ctx));
                     }
                 }
             }
@@ -751,21 +2817,203 @@ impl Handler<messages::incomming::StartMatch> for CahServer {
     }
 }
 
+impl Handler<messages::incomming::StartMatch> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::StartMatch, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_host(user_id)?;
+        room.expect_phase(MatchPhase::Lobby)?;
+        if room.players.len() < MIN_PLAYERS as usize {
+            return Err(MatchError::NotEnoughPlayers{needed: MIN_PLAYERS, actual: room.players.len() as u32});
+        }
+        if room.settings.teams != 0 && room.teams_with_players() < MIN_TEAMS {
+            return Err(MatchError::NotEnoughTeams{needed: MIN_TEAMS, actual: room.teams_with_players()});
+        }
+
+        room.start(&self.card_cache.read().unwrap());
+        CahServer::sync_phase_timer(room, &msg.match_name, ctx);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::Rematch> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::Rematch, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_host(user_id)?;
+        room.expect_phase(MatchPhase::GameOver)?;
+        if room.players.len() < MIN_PLAYERS as usize {
+            return Err(MatchError::NotEnoughPlayers{needed: MIN_PLAYERS, actual: room.players.len() as u32});
+        }
+
+        println!("room: {}. host: {} started a rematch", &msg.match_name, &user_id);
+        room.reset_for_rematch();
+        room.pass_czar();
+        room.start(&self.card_cache.read().unwrap());
+        CahServer::sync_phase_timer(room, &msg.match_name, ctx);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::KickPlayer> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::KickPlayer, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_host(user_id)?;
+        if msg.player_id == user_id {
+            return Err(MatchError::CannotKickYourself);
+        }
+        if msg.player_id == RANDO_CARDRISSIAN_ID {
+            return Err(MatchError::NotForRandoCardrissian);
+        }
+        let kicked_player = room.players.iter().chain(room.waitlist.iter()).find(|pim| pim.player.id == msg.player_id).ok_or(MatchError::PlayerNotFound(msg.player_id))?;
+        let kicked_bot = kicked_player.is_bot();
+        let kicked_message = ServerMessage::Kicked;
+        room.send_to_player(msg.player_id, kicked_message);
+
+        println!("room: {}. host: {} kicked player: {}", &msg.match_name, &user_id, msg.player_id);
+        // Bot ids are never handed out twice, so there is no need to ban them
+        if !kicked_bot {
+            room.banned.insert(msg.player_id);
+        }
+        room.leave(&msg.player_id);
+        CahServer::sync_phase_timer(room, &msg.match_name, ctx);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::AddBot> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::AddBot, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_host(user_id)?;
+        room.expect_phase(MatchPhase::Lobby)?;
+        let strategy = bots::strategy_by_name(&msg.strategy).ok_or_else(|| MatchError::UnknownBotStrategy(msg.strategy.clone()))?;
+
+        let bot_id = room.add_bot(strategy)?;
+        println!("room: {}. host: {} added the {} bot: {}", &msg.match_name, &user_id, &msg.strategy, bot_id);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::TransferHost> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::TransferHost, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_host(user_id)?;
+        let new_host = room.players.iter().find(|pim| pim.player.id == msg.player_id).ok_or(MatchError::PlayerNotFound(msg.player_id))?;
+        if new_host.is_rando() {
+            return Err(MatchError::NotForRandoCardrissian);
+        }
+        if new_host.is_bot() {
+            return Err(MatchError::NotForBots);
+        }
+
+        println!("room: {}. host: {} handed the host over to player: {}", &msg.match_name, &user_id, msg.player_id);
+        room.set_host(msg.player_id);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::LockMatch> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::LockMatch, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_host(user_id)?;
+        room.locked = msg.locked;
+
+        let match_locked_message = ServerMessage::MatchLocked {
+            locked: room.locked,
+        };
+        room.send_to_all_players(match_locked_message);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::ChangeSettings> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::ChangeSettings, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_host(user_id)?;
+        room.expect_phase(MatchPhase::Lobby)?;
+        msg.settings.validate().map_err(MatchError::InvalidSettings)?;
+        // Rando Cardrissian takes a seat as well
+        let seats_needed = room.players.iter().filter(|pim| !pim.is_rando()).count() + msg.settings.house_rules.rando_cardrissian as usize;
+        if seats_needed > msg.settings.max_players as usize {
+            return Err(MatchError::InvalidSettings(format!("{} seats are needed for everyone in the match", seats_needed)));
+        }
+
+        // Load the new decks before releasing the old ones, so decks in both settings stay in the cache
+        self.load_decks(&msg.settings.active_decks).map_err(MatchError::InvalidSettings)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).expect("We just found the match and nothing could have removed it");
+        let old_settings = std::mem::replace(&mut room.settings, msg.settings);
+        let teams_changed = old_settings.teams != room.settings.teams;
+        println!("room: {}. host: {} changed the settings to: {:?}", &msg.match_name, &user_id, room.settings);
+
+        let settings_changed_message = ServerMessage::SettingsChanged {
+            settings: room.settings.clone(),
+        };
+        room.send_to_all_players(settings_changed_message);
+        if teams_changed {
+            room.reassign_teams();
+        }
+        room.update_rando_cardrissian();
+        // There might be more seats now
+        room.promote_from_waitlist();
+        // God Is Dead gets rid of the czar, turning it off brings one back
+        let czar = room.next_eligible_czar(0);
+        if czar != room.czar {
+            room.czar = czar;
+            let new_czar_message = ServerMessage::NewCzar {
+                czar,
+            };
+            room.send_to_all_players(new_czar_message);
+        }
+        self.release_decks(&old_settings.active_decks);
+
+        Ok(())
+    }
+}
+
 impl Handler<messages::outgoing::AddCardToHand> for CahServer {
     type Result = ();
 
     fn handle(&mut self, msg: messages::outgoing::AddCardToHand, _ctx: &mut Context<Self>) -> Self::Result {
-        let mut message = json::parse(r#"{"type": "addCardToHand"}"#).unwrap();
-        message["card_content"] = JsonValue::String(msg.card.content);
-        message["card_id"] = JsonValue::Number(json::number::Number::from(msg.card.id));
-        let message_json = json::stringify(message);
-
-        if let Some(room) = self.matches.read().unwrap().get(&msg.room) {
+        if let Some(room) = self.matches.get_mut().unwrap().get_mut(&msg.room) {
             let user_id = msg.player.id;
-            if let Some(pim) = room.players.iter().find(|elem| elem.player.id == user_id){
-                if let Some(socket_actor) = &pim.socket_actor {
-                    socket_actor.do_send(messages::outgoing::Message(message_json));
-                }
+            if room.players.iter().any(|elem| elem.player.id == user_id) {
+                let add_card_to_hand_message = ServerMessage::AddCardToHand {
+                    card_id: msg.card.id,
+                    card_content: msg.card.content,
+                };
+                room.send_to_player(user_id, add_card_to_hand_message);
             }
         }
     }
@@ -773,50 +3021,64 @@ impl Handler<messages::outgoing::AddCardToHand> for CahServer {
 
 /// Handler for SubmitCard message
 impl Handler<messages::incomming::SubmitCard> for CahServer {
-    type Result = ();
+    type Result = Result<(), MatchError>;
 
-    fn handle(&mut self, msg: messages::incomming::SubmitCard, _: &mut Context<Self>) -> Self::Result {
-        if let Some(user_id) = self.get_user_id(&msg.token) {
-            let room_option = self.get_room_from_uuid(&user_id);
-            match room_option {
-                Some(room_name) => {
-                    let card_opt = self.card_cache.read().unwrap().get_card(msg.card_id);
-                    if card_opt.is_none() {
-                        println!("Submit request is invalid! The card doesn't exist in the card cache!");
-                        debug_assert!(false);
-                        return;
-                    }
-                    let card = card_opt.unwrap();
-                    println!("room: {}. player: {} submitted the card(id: {:?}, txt: {})", room_name, &user_id, msg.card_id, &card.content);
-                    let room = self.matches.get_mut().unwrap().get_mut(&room_name).unwrap();
-                    if let Some(pid_player) = room.players.iter_mut().find(|elem| elem.player.id == user_id) {
-                        let _ = pid_player.submitted_card.get_or_insert(card);
-                    } else {
-                        debug_assert!(false, "We managed to find ourselves with `self.get_user_id()`, however not while finding ourselves");
-                    }
-                    let everyone_submitted = room.has_everyone_submitted_card();
-                    if everyone_submitted {
-                        let mut card_ids: Vec<CardId> = Vec::with_capacity(room.players.len()-1);
-                        for player in &room.players {
-                            if room.czar != player.player.id {
-                                //TODO: WTF Why do I need to clone the Option here? I just want to read a value from it???
-                                let submitted_card_opt = player.submitted_card.clone();
-                                card_ids.push(submitted_card_opt.expect("We already checked with `Match::has_everyone_submitted_card()`").id.clone());
-                            }
-                        }
-                        let everyone_submitted_json = json!({
-                            "type": "everyone_submitted",
-                            "card_ids": card_ids
-                        });
-                        let everyone_submitted_msg = messages::outgoing::Message(everyone_submitted_json.to_string());
-                        room.send_to_all_players(everyone_submitted_msg);
-                    }
-                }
-                None => {
-                    println!("NO ROOM FOUND FOR PLAYER: {} ! While trying to submit the card_id: {}", &user_id, msg.card_id);
-                }
+    fn handle(&mut self, msg: messages::incomming::SubmitCard, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room_name = self.get_room_from_uuid(&user_id).ok_or(MatchError::NotInMatch)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&room_name).unwrap();
+
+        room.expect_phase(MatchPhase::Submitting)?;
+        if !room.plays_this_round(user_id) {
+            return Err(MatchError::CzarCannotSubmit);
+        }
+        if msg.card_ids.len() != room.pick_count() as usize {
+            return Err(MatchError::WrongCardCount{expected: room.pick_count(), actual: msg.card_ids.len() as u32});
+        }
+        if let Some((_, duplicate_card_id)) = msg.card_ids.iter().enumerate().find(|(i, card_id)| msg.card_ids[..*i].contains(card_id)) {
+            return Err(MatchError::DuplicateCard(*duplicate_card_id));
+        }
+
+        if room.team_of(user_id).is_some() && room.has_submitted(user_id) && room.players.iter().any(|pim| pim.player.id == user_id && pim.submitted_cards.is_empty()) {
+            return Err(MatchError::TeamAlreadySubmitted);
+        }
+        let pid_player = room.players.iter_mut().find(|elem| elem.player.id == user_id).ok_or(MatchError::NotInMatch)?;
+        if !pid_player.submitted_cards.is_empty() {
+            return Err(MatchError::AlreadySubmitted);
+        }
+        // Only cards from our own hand can be played
+        let mut cards = Vec::with_capacity(msg.card_ids.len());
+        for card_id in &msg.card_ids {
+            let card = pid_player.cards.iter().find(|card| card.id == *card_id).ok_or(MatchError::CardNotInHand(*card_id))?;
+            cards.push(card.clone());
+        }
+        // The write-ins go on the blank cards in the order they were submitted, the blank card in our hand stays blank
+        let blank_count = cards.iter().filter(|card| card.is_blank()).count();
+        if msg.write_ins.len() != blank_count {
+            return Err(MatchError::WrongWriteInCount{expected: blank_count as u32, actual: msg.write_ins.len() as u32});
+        }
+        for write_in in &msg.write_ins {
+            if write_in.chars().count() > MAX_WRITE_IN_LENGTH {
+                return Err(MatchError::InvalidWriteIn(format!("it can't be longer than {} characters", MAX_WRITE_IN_LENGTH)));
             }
+            Card::validate_content(write_in).map_err(MatchError::InvalidWriteIn)?;
         }
+        for (card, write_in) in cards.iter_mut().filter(|card| card.is_blank()).zip(msg.write_ins) {
+            card.content = write_in.trim().to_string();
+        }
+        pid_player.submitted_cards = cards;
+        println!("room: {}. player: {} submitted the cards(ids: {:?})", room_name, &user_id, &msg.card_ids);
+
+        let submit_accepted_message = ServerMessage::SubmitAccepted {
+            card_ids: msg.card_ids,
+        };
+        room.send_to_player(user_id, submit_accepted_message);
+        room.send_team_submitted(user_id);
+
+        room.end_submitting_if_done();
+        CahServer::sync_phase_timer(room, &room_name, ctx);
+
+        Ok(())
     }
 }
 
@@ -838,9 +3100,13 @@ impl Handler<messages::incomming::AddCard> for CahServer {
 
     fn handle(&mut self, msg: messages::incomming::AddCard, _: &mut Context<Self>) -> Self::Result {
         if let Some(_user_id) = self.sessions.read().unwrap().get(&msg.token) {
+            Card::validate_content(&msg.card_content)?;
+            if msg.is_black {
+                Card::validate_pick(msg.pick)?;
+            }
             let database = self.database.get_mut().unwrap();
 
-            database.execute(db::AddCard{deck_name: msg.deck_name, card_content: msg.card_content, is_black: msg.is_black}).wait()
+            database.execute(db::AddCard{deck_name: msg.deck_name, card_content: msg.card_content, is_black: msg.is_black, pick: msg.pick}).wait()
                 .map_err(|db_err| format!("Db error: {}", db_err))
         } else {
             Err(str!("Cannot find logged in player with that session token, is it invalid?"))
@@ -866,43 +3132,123 @@ impl Handler<messages::incomming::DelCard> for CahServer {
 }
 
 impl Handler<messages::incomming::RevealCard> for CahServer {
-    type Result = ();
+    type Result = Result<(), MatchError>;
 
-    fn handle(&mut self, msg: messages::incomming::RevealCard, _: &mut Context<Self>) -> Self::Result {
-        if let Some(user_id) = self.get_user_id(&msg.token) {
-            let matches = self.matches.get_mut().unwrap();
-            match matches.get_mut(&msg.match_name) {
-                Some(room) => {                    
-                    if user_id == room.czar && room.has_everyone_submitted_card() {
-                        let card_opt = self.card_cache.read().unwrap().get_card(msg.card_id);
-                        if card_opt.is_none() {
-                            println!("Submit request is invalid! The card doesn't exist in the card cache!");
-                            debug_assert!(false);
-                            return;
-                        }
-                        let card = card_opt.unwrap();
-                        println!("room: {}. czar player: {} revealed the card: {:?}", &msg.match_name, &user_id, &card.id);
-
-                        let reveal_card_json = json!({
-                            "type": "revealCard",
-                            "card_id": card.id,
-                            "card_content": card.content,
-                        });
-                        let reveal_card_msg = messages::outgoing::Message(reveal_card_json.to_string());
-                        room.send_to_all_players(reveal_card_msg);
-                    } else {
-                        if user_id != room.czar {
-                            println!("The user trying to submit a reveal card is not the czar!");
-                        } else {
-                            println!("In this room not everyone has submitted their card yet! (Meaning they are not ready to reveal yet).");
-                        }
-                    }
-                }
-                None => {
-                    println!("NO ROOM FOUND FOR PLAYER: {} ! While trying to reveal the card_id: {}", &user_id, msg.card_id);
-                }
-            }
+    fn handle(&mut self, msg: messages::incomming::RevealCard, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_phase(MatchPhase::Revealing)?;
+        if user_id != room.czar {
+            return Err(MatchError::NotCzar);
+        }
+        room.reveal_submission(msg.submission_id)?;
+        println!("room: {}. czar player: {} revealed the submission: {}", &msg.match_name, &user_id, msg.submission_id);
+
+        // A single submission survives without anyone having to eliminate anything
+        CahServer::judge_survivor(room, &msg.match_name, ctx)
+    }
+}
+
+impl Handler<messages::incomming::EliminateSubmission> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::EliminateSubmission, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.eliminate_submission(user_id, msg.submission_id)?;
+        println!("room: {}. player: {} eliminated the submission: {}", &msg.match_name, &user_id, msg.submission_id);
+
+        CahServer::judge_survivor(room, &msg.match_name, ctx)
+    }
+}
+
+impl Handler<messages::incomming::RebootHand> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::RebootHand, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.reboot_hand(user_id)?;
+        println!("room: {}. player: {} traded in a point for a new hand", &msg.match_name, &user_id);
+        if let Some(team) = room.team_of(user_id) {
+            room.send_team_hands(team);
         }
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::ConfessCard> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::ConfessCard, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.confess_card(user_id, msg.card_id)?;
+        println!("room: {}. player: {} confessed not to understand the card: {}", &msg.match_name, &user_id, msg.card_id);
+        if let Some(team) = room.team_of(user_id) {
+            room.send_team_hands(team);
+        }
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::Vote> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::Vote, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.vote(user_id, msg.submission_id)?;
+        println!("room: {}. player: {} voted", &msg.match_name, &user_id);
+
+        if room.has_everyone_voted() {
+            CahServer::judge_votes(room, &msg.match_name, ctx);
+        }
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::JoinTeam> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::JoinTeam, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.join_team(user_id, msg.team)?;
+        println!("room: {}. player: {} joined team: {}", &msg.match_name, &user_id, msg.team);
+
+        Ok(())
+    }
+}
+
+impl Handler<messages::incomming::Chat> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::Chat, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.send_chat_message(user_id, &msg.text)
+    }
+}
+
+impl Handler<messages::incomming::TeamChat> for CahServer {
+    type Result = Result<(), MatchError>;
+
+    fn handle(&mut self, msg: messages::incomming::TeamChat, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.send_team_message(user_id, &msg.text)
     }
 }
 
@@ -915,181 +3261,127 @@ impl Handler<crate::StopServer> for CahServer {
 }
 
 impl Handler<messages::incomming::CzarChoice> for CahServer {
-    type Result = ();
+    type Result = Result<(), MatchError>;
 
-    fn handle(&mut self, msg: messages::incomming::CzarChoice, ctx: &mut Context<Self>) {
-        if let Some(user_id) = self.get_user_id(&msg.token) {
-            let matches = self.matches.get_mut().unwrap();
-            match matches.get_mut(&msg.match_name) {
-                Some(room) => {                    
-                    if user_id == room.czar && room.has_everyone_submitted_card() {
-                        let card_opt = self.card_cache.read().unwrap().get_card(msg.card_id);
-                        if card_opt.is_none() {
-                            println!("Submit request is invalid! The card doesn't exist in the card cache!");
-                            debug_assert!(false);
-                            return;
-                        }
-                        let card = card_opt.unwrap();
-                        println!("room: {}. czar player: {} choose the card: {:?}", &msg.match_name, &user_id, &card.id);
-
-                        let czar_submitted_json = json!({
-                            "type": "czar_choice",
-                            "card_id": card.id,
-                        });
-                        let czar_submitted_msg = messages::outgoing::Message(czar_submitted_json.to_string());
-                        room.send_to_all_players(czar_submitted_msg);
-
-                        let victorious_player_opt = room.players.iter_mut().find(|player_in_match| match &player_in_match.submitted_card {
-                            Some(submitted_card) => submitted_card.id == card.id,
-                            None => false,
-                        });
-                        
-                        debug_assert!(victorious_player_opt.is_some());
-                        if let Some(victorious_player) = victorious_player_opt {
-                            victorious_player.points += 1;
-                            let victorious_player_id = victorious_player.player.id;
-                            let did_player_win = victorious_player.points >= room.points_to_win;
-                            {
-                                let player_won_round_json = json!({
-                                    "type": "roundWon",
-                                    "player_id": victorious_player_id,
-                                });
-                                room.send_to_all_players(messages::outgoing::Message(player_won_round_json.to_string()));
-                            }
-
-
-                            if did_player_win {
-                                let player_won_msg = json!({
-                                    "type": "playerWon",
-                                    "player_id": victorious_player_id,
-                                });
-                                room.send_to_all_players(messages::outgoing::Message(player_won_msg.to_string()));
-                            }
-
-                            ctx.run_later(Duration::from_millis(3000), move |cah, _ctx| {
-                                let matches = cah.matches.get_mut().unwrap();
-                                let card_cache = cah.card_cache.read().unwrap();
-                                match matches.get_mut(&msg.match_name) {
-                                    Some(room) => { 
-                                        for player_in_match in &mut room.players {
-                                            if player_in_match.player.id != room.czar {
-                                                if let Some(submitted_card) = player_in_match.submitted_card.clone() {
-                                                    if let Some(card_pos) = player_in_match.cards.iter().position(|card| card.id == submitted_card.id) {
-                                                        if let Some(socket_connection) = player_in_match.socket_actor.clone() {
-                                                            let msg_json = json!({
-                                                                "type": "removeCard",
-                                                                "card_id": player_in_match.cards[card_pos].id
-                                                            });
-                                                            let msg_json_string = msg_json.to_string();
-                                                            socket_connection.do_send( messages::outgoing::Message(msg_json_string) );
-                                                        }
-
-                                                        player_in_match.cards.remove(card_pos);
-                                                        CahServer::add_random_card(&card_cache, &room.active_decks, player_in_match);
-                                                    }
-                                                }
-                                            }
-
-                                            player_in_match.submitted_card = None;
-                                        }
-
-                                        let new_round_json = json!({
-                                            "type": "newRound"
-                                        });
-                                        room.send_to_all_players(messages::outgoing::Message(new_round_json.to_string()));
-
-                                        {
-                                            if let Some(card) = card_cache.get_random_black_card(&room.active_decks) {
-                                                let new_black_card_json = json!({
-                                                    "type": "newBlack",
-                                                    "card_id": card.id,
-                                                    "card_content": card.content
-                                                });
-                                                room.send_to_all_players( messages::outgoing::Message(new_black_card_json.to_string()) );
-                                            }
-                                        }
-
-                                        let czar_index_opt = room.players.iter().position(|pim| &pim.player.id == &room.czar);
-                                        match czar_index_opt {
-                                            Some(czar_index) => {
-                                                if !room.players.is_empty() {
-                                                    let new_czar_index = increment_and_wrap(czar_index, room.players.len(), 0);
-                                                    room.czar = room.players[new_czar_index].player.id;
-                                                }
-                                            },
-                                            None => { 
-                                                // Right now, if it can't find the last czar, it will default to p1, however thats unfair for the last player.
-                                                // However, this should "never" happen as when the player disconnects it already gets handled and czar gets handed over.
-                                                // Which makes this soludion here solid.
-                                                if !room.players.is_empty() { room.czar = room.players[0].player.id; } 
-                                            }
-                                        }
-
-                                        let new_czar_json = json!({
-                                            "type": "newCzar",
-                                            "czar": room.czar
-                                        });
-                                        room.send_to_all_players(messages::outgoing::Message(new_czar_json.to_string()));
-                                    },
-                                    None => {},
-                                }              
-                            });
-                        }
-                    }
-                }
-                None => {
-                    println!("NO ROOM FOUND FOR PLAYER: {} ! While trying to submit the card_id: {}", &user_id, msg.card_id);
-                }
-            }
+    fn handle(&mut self, msg: messages::incomming::CzarChoice, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(MatchError::NotLoggedIn)?;
+        let room = self.matches.get_mut().unwrap().get_mut(&msg.match_name).ok_or_else(|| MatchError::MatchNotFound(msg.match_name.clone()))?;
+
+        room.expect_phase(MatchPhase::Judging)?;
+        // Nobody is czar with God Is Dead, so this comes before the czar check
+        if room.settings.house_rules.god_is_dead {
+            return Err(MatchError::ReplacedByHouseRule("godIsDead"));
+        }
+        if user_id != room.czar {
+            return Err(MatchError::NotCzar);
+        }
+        if room.settings.house_rules.survival_of_the_fittest {
+            return Err(MatchError::ReplacedByHouseRule("survivalOfTheFittest"));
+        }
+        if msg.submission_id >= room.submissions.len() {
+            return Err(MatchError::UnknownSubmission(msg.submission_id));
         }
+        println!("room: {}. czar player: {} choose the submission: {}", &msg.match_name, &user_id, msg.submission_id);
+
+        CahServer::judge_submission(room, &msg.match_name, msg.submission_id, ctx)
     }
 }
 
-/// Handler for Disconnect message.
-impl Handler<messages::incomming::Disconnect> for CahServer {
-    type Result = ();
+/// Handler for `ListRooms` message.
+impl Handler<messages::incomming::ListRooms> for CahServer {
+    type Result = MessageResult<messages::incomming::ListRooms>;
 
-    fn handle(&mut self, msg: messages::incomming::Disconnect, _: &mut Context<Self>) {
-        println!("Someone disconnected");
-
-        // let socket_actor_entrty = self.socket_actors.entry(msg.token.clone());
-        // match socket_actor_entrty {
-        //     Entry::Occupied(entry) => {
-        //         let (_saved_token, _socket_actor) = entry.remove_entry();
-        //         //actually we don't need to stop, if all Addr's go out of scope, the actor is stopped automatically
-        //         //socket_actor.stop()
-        //     },
-        //     _ => {},
-        // }
-        // let _ = self.socket_actors.remove(&msg.token);
-        
-        if let Some(user_id) = self.get_user_id(&msg.token) {
-            // remove address
-            if self.sessions.get_mut().unwrap().remove(&msg.token).is_some() {
-                // remove session from all rooms
-                for (_name, room) in self.matches.get_mut().unwrap() {
-                    room.players.retain(|elem| elem.player.id != user_id);
+    fn handle(&mut self, msg: messages::incomming::ListRooms, _: &mut Context<Self>) -> Self::Result {
+        let user_id_opt = self.get_user_id(&msg.cookie_token);
+        let mut rooms = Vec::<MatchInfo>::new();
+
+        for (name, room) in self.matches.read().unwrap().iter() {
+            // Invite only matches are kept secret from everyone outside of them
+            if let MatchAccess::InviteCode(_) = room.access {
+                if !room.players.iter().any(|pim| Some(pim.player.id) == user_id_opt) {
+                    continue;
                 }
             }
+
+            rooms.push(MatchInfo{
+                name: name.clone(),
+                host: room.host,
+                player_count: room.players.len() as u32,
+                spectator_count: room.spectators.len() as u32,
+                waitlist_count: room.waitlist.len() as u32,
+                started: room.phase != MatchPhase::Lobby,
+                locked: room.locked,
+                private: !room.access.is_public(),
+                settings: room.settings.clone(),
+            });
         }
-        // send message to other users
-        // for room in rooms {
-        //     self.send_message(&room, "Someone disconnected", Uuid::nil());
-        // }
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+
+        MessageResult(rooms)
     }
 }
 
-/// Handler for `ListRooms` message.
-impl Handler<messages::incomming::ListRooms> for CahServer {
-    type Result = MessageResult<messages::incomming::ListRooms>;
+impl Handler<messages::incomming::CreateMatch> for CahServer {
+    type Result = Result<Option<String>, String>;
 
-    fn handle(&mut self, _: messages::incomming::ListRooms, _: &mut Context<Self>) -> Self::Result {
-        let mut rooms = Vec::<String>::new();
+    fn handle(&mut self, msg: messages::incomming::CreateMatch, _: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(str!("Cannot find logged in player with that session token, is it invalid?"))?;
 
-        for key in self.matches.read().unwrap().keys() {
-            rooms.push(key.to_owned())
+        let match_name = msg.match_name.trim().to_string();
+        if match_name.is_empty() || match_name.len() > MAX_MATCH_NAME_LENGTH {
+            return Err(format!("The match name should be between 1 and {} characters long", MAX_MATCH_NAME_LENGTH));
+        }
+        if self.matches.read().unwrap().contains_key(&match_name) {
+            return Err(format!("A match with the name '{}' already exists", match_name));
         }
+        msg.settings.validate()?;
+        let access = match (&msg.password, msg.invite_only) {
+            (Some(_), true) => return Err(str!("A match can either have a password or be invite only, not both")),
+            (Some(password), false) if password.is_empty() => return Err(str!("The password of a match can't be empty")),
+            (Some(password), false) => MatchAccess::with_password(password),
+            (None, true) => MatchAccess::with_new_invite_code(),
+            (None, false) => MatchAccess::Public,
+        };
+        let invite_code = access.invite_code();
 
-        MessageResult(rooms)
+        // The decks stay loaded for as long as the match exists, `DeleteMatch` releases them again
+        self.load_decks(&msg.settings.active_decks)?;
+
+        println!("Player: {} created the match: '{}' with settings: {:?}", user_id, match_name, msg.settings);
+        self.matches.get_mut().unwrap().insert(match_name, Match::new(msg.settings, user_id, access));
+
+        Ok(invite_code)
+    }
+}
+
+impl Handler<messages::incomming::DeleteMatch> for CahServer {
+    type Result = Result<(), String>;
+
+    fn handle(&mut self, msg: messages::incomming::DeleteMatch, ctx: &mut Context<Self>) -> Self::Result {
+        let user_id = self.get_user_id(&msg.token).ok_or(str!("Cannot find logged in player with that session token, is it invalid?"))?;
+
+        let matches = self.matches.get_mut().unwrap();
+        match matches.get_mut(&msg.match_name) {
+            Some(room) => {
+                // Once everyone left there is no host anymore, so anyone can clean up the match
+                if room.host != user_id && room.host != PlayerNilId {
+                    return Err(str!("Only the host can delete a match"));
+                }
+
+                let match_deleted_message = ServerMessage::MatchDeleted;
+                room.send_to_all_players(match_deleted_message);
+            },
+            None => return Err(format!("Cannot find the room named '{}'", msg.match_name)),
+        }
+
+        println!("Player: {} deleted the match: '{}'", user_id, msg.match_name);
+        if let Some(room) = matches.remove(&msg.match_name) {
+            if let Some((timer_handle, _)) = room.timer {
+                ctx.cancel_future(timer_handle);
+            }
+            self.release_decks(&room.settings.active_decks);
+        }
+
+        Ok(())
     }
 }
@@ -88,10 +88,14 @@ impl Database {
                                         card_id INTEGER PRIMARY KEY UNIQUE,
                                         deck VARCHAR(64) NOT NULL,
                                         card_content VARCHAR(255) NOT NULL,
-                                        is_black BIT NOT NULL
+                                        is_black BIT NOT NULL,
+                                        pick INTEGER NOT NULL DEFAULT 1
                                         );
                                         ";
             let _exec_res = connection.execute(create_tables_stmt, NO_PARAMS).map_err( |err| println!("There was an error initializing db: {:?}", err) );
+
+            // Databases created before black cards had a pick count are missing the column, this fails harmlessly when it already exists.
+            let _migrate_res = connection.execute("ALTER TABLE cards ADD COLUMN pick INTEGER NOT NULL DEFAULT 1", NO_PARAMS);
         } else {
             println!("ERROR: Couldn't aquire a sqlite3 connection, and the default tables are not created");
         }
@@ -213,20 +217,20 @@ impl DbQuery for GetCardDeck {
 
     fn execute(&mut self, connection: Connection) -> Result<Self::Item, DbError> {
         let get_cards_stmt = "
-        SELECT card_id, card_content, is_black FROM cards WHERE deck=?1 
+        SELECT card_id, card_content, is_black, pick FROM cards WHERE deck=?1 
         ";
         
         let mut get_cards_query = connection.prepare(get_cards_stmt).unwrap();
-        let cards_iterator = get_cards_query.query_map::<(CardId, String, bool), _, _>(params![self.deck_name], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)) )?;
+        let cards_iterator = get_cards_query.query_map::<(CardId, String, bool, u32), _, _>(params![self.deck_name], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)) )?;
         let mut card_deck = CardDeck::default();
         card_deck.deck_name = self.deck_name.clone();
         let mut atleast_one_card = false;
         for card_result in cards_iterator {
-            let (card_id, card_content, is_black): (CardId, String, bool)  = card_result?;
+            let (card_id, card_content, is_black, pick): (CardId, String, bool, u32)  = card_result?;
 
             atleast_one_card = true;
             
-            let card = Card{id: card_id, content: card_content};
+            let card = Card{id: card_id, content: card_content, pick};
             if is_black {
                 card_deck.black_cards.push(card);
             } else {
@@ -246,15 +250,17 @@ pub struct AddCard {
     pub deck_name: String,
     pub card_content: String,
     pub is_black: bool,
+    /// The amount of white cards a black card asks for, white cards always store 1
+    pub pick: u32,
 }
 impl DbQuery for AddCard {
     type Item = CardId;
 
     fn execute(&mut self, connection: Connection) -> Result<CardId, DbError> {
-        let insert_card_stmt = "INSERT INTO cards (deck, card_content, is_black) VALUES (?1, ?2, ?3)";
+        let insert_card_stmt = "INSERT INTO cards (deck, card_content, is_black, pick) VALUES (?1, ?2, ?3, ?4)";
         let amount_inserted = connection.execute(
             insert_card_stmt, 
-            params![self.deck_name, self.card_content, self.is_black])
+            params![self.deck_name, self.card_content, self.is_black, self.pick])
             .map_err(|_db_err| DbError{additional_info: str!("Inserting player went wrong!")} )?;
 
         if amount_inserted != 1 {
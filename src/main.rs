@@ -31,14 +31,25 @@ use str_macro::str;
 pub mod cah_server;
 pub mod messages;
 pub mod db;
+pub mod bots;
 
-use cah_server::CardId;
+use cah_server::{CardId, PlayerId, MatchError};
+use messages::incomming::{self, ClientMessage};
+use messages::outgoing::ServerMessage;
 use db::Pool;
 
 /// How often heartbeat pings are sent for the websockets
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 /// How long before lack of client response causes a timeout
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a new websocket gets to say hello
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+/// Close code for clients speaking a protocol version this server doesn't support
+const CLOSE_CODE_UNSUPPORTED_PROTOCOL: u16 = 4000;
+/// Close code for clients which didn't say hello in time
+const CLOSE_CODE_HELLO_TIMEOUT: u16 = 4001;
+/// Close code for sockets which couldn't be connected to their match
+const CLOSE_CODE_CONNECT_FAILED: u16 = 4002;
 // A constant all cookies to be signed with
 const COOKIE_SIGNED_KEY: [u8; 32] = [
     200,  2,  0,  0,  34,  75,  0,  0,
@@ -68,9 +79,16 @@ fn session_get_cookie_token_or_default(session: &Session) -> CookieToken {
 fn ws_index(r: HttpRequest, stream: web::Payload, session: Session, server_address: web::Data<Addr<cah_server::CahServer>>, path: web::Path<(String,)>) -> Result<HttpResponse, Error> {
     println!("{:?}", r);
     println!("Trying to connect to: {}", &path.0);
+    // Spectators don't need to be logged in
+    let spectating = r.query_string().split('&').any(|param| param == "spectate" || param.starts_with("spectate="));
+    if spectating {
+        let cookie_token = session_get_cookie_token_or_default(&session);
+        return ws::start(MyWebSocket::new(cookie_token, server_address.get_ref().clone(), path.0.clone(), true), &r, stream);
+    }
+
     // let cookie_token = session_get_cookie_token_or_default(&session);
     if let Ok(Some(cookie_token)) = session.get::<CookieToken>("ct") {
-        let res = ws::start(MyWebSocket::new(cookie_token, server_address.get_ref().clone(), path.0.clone()), &r, stream);
+        let res = ws::start(MyWebSocket::new(cookie_token, server_address.get_ref().clone(), path.0.clone(), false), &r, stream);
         println!("{:?}", res.as_ref().unwrap());
         res
     } else {
@@ -79,13 +97,19 @@ fn ws_index(r: HttpRequest, stream: web::Payload, session: Session, server_addre
     }
 }
 
-/// handler with path parameters like `/user/{name}/`
-fn get_join_match(req: HttpRequest, session: Session, server_address: web::Data<Addr<cah_server::CahServer>>, path: web::Path<String>) -> Result<HttpResponse, Error> {
-    println!("{:?}", req);
+// Sent in the body like the login, so the password stays out of the logs and the browser history
+#[derive(Serialize, Deserialize)]
+pub struct JoinMatchRequestPayload {
+    pub password: Option<String>,
+    pub invite: Option<String>,
+}
 
+/// handler with path parameters like `/user/{name}/`
+fn post_join_match(_r: HttpRequest, session: Session, server_address: web::Data<Addr<cah_server::CahServer>>, path: web::Path<String>, body: web::Form<JoinMatchRequestPayload>) -> Result<HttpResponse, Error> {
     if let Ok(Some(cookie_token)) = session.get::<CookieToken>("ct") {
         let match_name = path.clone();
-        let async_req = server_address.send(messages::incomming::JoinMatch{match_name: match_name, token: cookie_token});
+        let body = body.into_inner();
+        let async_req = server_address.send(messages::incomming::JoinMatch{match_name, token: cookie_token, password: body.password, invite_code: body.invite});
         let res = async_req.wait();
         match res {
             Ok(Ok(game_state)) => Ok(HttpResponse::build(StatusCode::OK).body(serde_json::to_string(&game_state).unwrap())),
@@ -105,7 +129,47 @@ fn get_list_rooms(_r: HttpRequest, session: Session, server_address: web::Data<A
     
     server_address.send(messages::incomming::ListRooms{cookie_token: token})
         .map_err(Error::from)
-        .map( |matches| { HttpResponse::Ok().body(json::stringify(matches)) })
+        .map( |matches| { HttpResponse::Ok().body(serde_json::to_string(&matches).unwrap()) })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateMatchPayload {
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: cah_server::MatchSettings,
+    // Makes the match private, only players who know it can join
+    pub password: Option<String>,
+    // Makes the match private, the server responds with an invite code to join it
+    #[serde(default)]
+    pub invite_only: bool,
+}
+
+fn post_create_match(_r: HttpRequest, body: web::Json<CreateMatchPayload>, session: Session, server_address: web::Data<Addr<cah_server::CahServer>>) -> impl Future<Item = HttpResponse, Error = Error> {
+    let token = session_get_cookie_token_or_default(&session);
+    let payload = body.into_inner();
+
+    server_address.send(messages::incomming::CreateMatch{token, match_name: payload.name, settings: payload.settings, password: payload.password, invite_only: payload.invite_only})
+        .map_err(Error::from)
+        .map( |create_result| {
+            match create_result {
+                Ok(Some(invite_code)) => HttpResponse::Ok().body(serde_json::json!({"invite_code": invite_code}).to_string()),
+                Ok(None) => HttpResponse::Ok().body("Succesfully created match!"),
+                Err(error_message) => HttpResponse::build(StatusCode::BAD_REQUEST).body(error_message)
+            }
+        })
+}
+
+fn delete_match(_r: HttpRequest, session: Session, server_address: web::Data<Addr<cah_server::CahServer>>, path: web::Path<(String,)>) -> impl Future<Item = HttpResponse, Error = Error> {
+    let token = session_get_cookie_token_or_default(&session);
+
+    server_address.send(messages::incomming::DeleteMatch{token, match_name: path.into_inner().0})
+        .map_err(Error::from)
+        .map( |delete_result| {
+            match delete_result {
+                Ok(()) => HttpResponse::Ok().finish(),
+                Err(error_message) => HttpResponse::build(StatusCode::BAD_REQUEST).body(error_message)
+            }
+        })
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -156,6 +220,10 @@ pub struct MyWebSocket {
     hb: Instant,
     cookie_token: CookieToken,
     match_name: String,
+    // Connected with `?spectate`, only watching the match
+    spectating: bool,
+    // The protocol version the client said hello with, None until it did
+    protocol_version: Option<u32>,
     
     server_addr: Addr<cah_server::CahServer>,
 }
@@ -173,22 +241,25 @@ impl Actor for MyWebSocket {
     type Context = ws::WebsocketContext<Self>;
 
     /// Method is called on actor start. We start the heartbeat process here.
+    /// The socket is only connected to its match once the client said hello, see `MyWebSocket::hello`
     fn started(&mut self, ctx: &mut Self::Context) {
         self.hb(ctx);
 
-        let addr = ctx.address();
-        let connect_request = self.server_addr.send(messages::incomming::SocketConnectMatch{addr: addr.clone(), token: self.cookie_token.clone()});
-        match connect_request.wait() {
-            Ok(_) => {},
-            Err(err_msg) => { 
-                println!("ERROR while connecting websocket: '{}'", err_msg);
-                self.server_addr.do_send(messages::incomming::Disconnect{token: self.cookie_token.clone()});
+        ctx.run_later(HELLO_TIMEOUT, |act, ctx| {
+            if act.protocol_version.is_none() {
+                println!("Websocket didn't say hello in time, closing it");
+                MyWebSocket::close_with(CLOSE_CODE_HELLO_TIMEOUT, "Say hello with the protocol version first", ctx);
             }
-        }
+        });
     }
 
-    fn stopping(&mut self, _ctx: &mut Self::Context) -> Running {
-        self.server_addr.do_send(messages::incomming::Leavematch{match_name: self.match_name.clone(), token: self.cookie_token.clone()});
+    fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
+        if self.spectating {
+            self.server_addr.do_send(messages::incomming::StopSpectating{match_name: self.match_name.clone(), addr: ctx.address()});
+            return Running::Stop;
+        }
+
+        self.server_addr.do_send(messages::incomming::SocketDisconnect{match_name: self.match_name.clone(), token: self.cookie_token, addr: ctx.address()});
 
         Running::Stop
     }
@@ -209,58 +280,103 @@ impl StreamHandler<ws::Message, ws::ProtocolError> for MyWebSocket {
             ws::Message::Text(text) => {
                 println!("WS: {:?}", &text);
 
-                if let Ok(json_message) = json::parse(&text) {
-                    if !json_message["type"].is_string() {
-                        ctx.text(format!("{:?}", HttpResponse::build(StatusCode::BAD_REQUEST).reason("'type' is not available in json request").finish()));
+                let json_message: serde_json::Value = match serde_json::from_str(&text) {
+                    Ok(json_message) => json_message,
+                    Err(parse_err) => {
+                        MyWebSocket::send_to_client(MatchError::MalformedMessage(parse_err.to_string()).to_message("", None), ctx);
+                        return;
                     }
-                    match json_message["type"].as_str().unwrap() {
-                        "submitCard" => {
-                            if json_message["card_id"].is_number() {
-                                let card_id: CardId = json_message["card_id"].as_number().unwrap().into();
-                                // println!("Player {} has submitted card with id:{}", self.user_id, json_message["card_id"].as_number().unwrap());
-                                let submit_card = messages::incomming::SubmitCard{token: self.cookie_token, card_id: card_id};
-                                self.server_addr.do_send(submit_card);
-                            } else {
-                                ctx.text(format!("{:?}", HttpResponse::build(StatusCode::BAD_REQUEST).reason("'card_id' is not a 'number' available in json request").finish()));
-                            }
-                            // if json_message["card_content"].is_string() {
-                            //     let card_content: String = json_message["card_id"].as_str().unwrap().to_string();
-                            //     let submit_card = messages::incomming::SubmitCard{token: self.cookie_token, card_id: card_content};
-                            //     self.server_addr.do_send(submit_card);
-                            // } else {
-                            //     ctx.text(format!("{:?}", HttpResponse::build(StatusCode::BAD_REQUEST).reason("'card_content' is not a 'string' available in json request").finish()));
-                            // }
-                        },
-                        "startGame" => {
-                            self.server_addr.do_send(messages::incomming::StartMatch{token: self.cookie_token, match_name: self.match_name.clone()});
-                        },
-                        "revealCard" => {
-                            if json_message["card_id"].is_number() {
-                                let card_id: CardId = json_message["card_id"].as_number().unwrap().into();
-                                // println!("Player {} has submitted card with id:{}", self.user_id, json_message["card_id"].as_number().unwrap());
-                                let msg = messages::incomming::RevealCard{token: self.cookie_token.clone(), match_name: self.match_name.clone(), card_id: card_id};
-                                self.server_addr.do_send(msg);
-                            } else {
-                                ctx.text(format!("{:?}", HttpResponse::build(StatusCode::BAD_REQUEST).reason("'card_id' is not a 'number' available in json request").finish()));
-                            }
+                };
+                // Errors name the action they reject, so the client knows what to undo
+                let action = json_message["type"].as_str().unwrap_or("").to_string();
+                // And the id the client gave the message, so it can match our answer to it
+                let request_id = match &json_message["request_id"] {
+                    serde_json::Value::Null => None,
+                    id => match id.as_u64() {
+                        Some(id) => Some(id),
+                        None => {
+                            let err = MatchError::MalformedMessage("request_id has to be a positive integer".to_string());
+                            MyWebSocket::send_to_client(err.to_message(&action, None), ctx);
+                            return;
                         },
-                        "czarChoice" => {
-                            if json_message["card_id"].is_number() {
-                                let card_id: CardId = json_message["card_id"].as_number().unwrap().into();
-                                // println!("Player {} has submitted card with id:{}", self.user_id, json_message["card_id"].as_number().unwrap());
-                                let czar_choice = messages::incomming::CzarChoice{token: self.cookie_token, match_name: self.match_name.clone(), card_id: card_id };
-                                self.server_addr.do_send(czar_choice);
-                            } else {
-                                ctx.text(format!("{:?}", HttpResponse::build(StatusCode::BAD_REQUEST).reason("'card_id' is not a 'number' available in json request").finish()));
-                            }
-                        },
-                        _ => {
-                            println!("Unknown type of message received in websocket. type: {}, only supported types: submitCard, startGame. Full json message: {}", json_message["type"].as_str().unwrap(), text);
-                        }
+                    },
+                };
+                let client_message = match serde_json::from_value::<ClientMessage>(json_message) {
+                    Ok(client_message) => client_message,
+                    Err(parse_err) => {
+                        MyWebSocket::send_to_client(MatchError::MalformedMessage(parse_err.to_string()).to_message(&action, request_id), ctx);
+                        return;
                     }
-
-                } else {
-                    ctx.text(text);
+                };
+
+                let token = self.cookie_token;
+                let match_name = self.match_name.clone();
+                match client_message {
+                    ClientMessage::Hello{protocol_version, client, last_seq} => {
+                        self.hello(protocol_version, &client, last_seq, request_id, ctx);
+                    },
+                    _ if self.protocol_version.is_none() => {
+                        MyWebSocket::send_to_client(MatchError::HelloRequired.to_message(&action, request_id), ctx);
+                    },
+                    _ if self.spectating => {
+                        MyWebSocket::send_to_client(MatchError::SpectatorsCannotPlay.to_message(&action, request_id), ctx);
+                    },
+                    ClientMessage::SubmitCard{card_ids, write_ins} => {
+                        self.send_match_action("submitCard", request_id, incomming::SubmitCard{token, card_ids, write_ins}, ctx);
+                    },
+                    ClientMessage::StartGame => {
+                        self.send_match_action("startGame", request_id, incomming::StartMatch{token, match_name}, ctx);
+                    },
+                    ClientMessage::LeaveMatch => {
+                        self.server_addr.do_send(incomming::Leavematch{match_name, token});
+                        MyWebSocket::send_ack("leaveMatch", request_id, ctx);
+                        ctx.stop();
+                    },
+                    ClientMessage::Rematch => {
+                        self.send_match_action("rematch", request_id, incomming::Rematch{token, match_name}, ctx);
+                    },
+                    ClientMessage::KickPlayer{player_id} => {
+                        self.send_match_action("kickPlayer", request_id, incomming::KickPlayer{token, match_name, player_id}, ctx);
+                    },
+                    ClientMessage::TransferHost{player_id} => {
+                        self.send_match_action("transferHost", request_id, incomming::TransferHost{token, match_name, player_id}, ctx);
+                    },
+                    ClientMessage::AddBot{strategy} => {
+                        self.send_match_action("addBot", request_id, incomming::AddBot{token, match_name, strategy}, ctx);
+                    },
+                    ClientMessage::LockMatch{locked} => {
+                        self.send_match_action("lockMatch", request_id, incomming::LockMatch{token, match_name, locked}, ctx);
+                    },
+                    ClientMessage::ChangeSettings{settings} => {
+                        self.send_match_action("changeSettings", request_id, incomming::ChangeSettings{token, match_name, settings}, ctx);
+                    },
+                    ClientMessage::RevealCard{submission_id} => {
+                        self.send_match_action("revealCard", request_id, incomming::RevealCard{token, match_name, submission_id}, ctx);
+                    },
+                    ClientMessage::CzarChoice{submission_id} => {
+                        self.send_match_action("czarChoice", request_id, incomming::CzarChoice{token, match_name, submission_id}, ctx);
+                    },
+                    ClientMessage::EliminateSubmission{submission_id} => {
+                        self.send_match_action("eliminateSubmission", request_id, incomming::EliminateSubmission{token, match_name, submission_id}, ctx);
+                    },
+                    ClientMessage::Vote{submission_id} => {
+                        self.send_match_action("vote", request_id, incomming::Vote{token, match_name, submission_id}, ctx);
+                    },
+                    ClientMessage::JoinTeam{team} => {
+                        self.send_match_action("joinTeam", request_id, incomming::JoinTeam{token, match_name, team}, ctx);
+                    },
+                    ClientMessage::Chat{text} => {
+                        self.send_match_action("chat", request_id, incomming::Chat{token, match_name, text}, ctx);
+                    },
+                    ClientMessage::TeamChat{text} => {
+                        self.send_match_action("teamChat", request_id, incomming::TeamChat{token, match_name, text}, ctx);
+                    },
+                    ClientMessage::RebootHand => {
+                        self.send_match_action("rebootHand", request_id, incomming::RebootHand{token, match_name}, ctx);
+                    },
+                    ClientMessage::ConfessCard{card_id} => {
+                        self.send_match_action("confessCard", request_id, incomming::ConfessCard{token, match_name, card_id}, ctx);
+                    },
                 }
             },
             ws::Message::Binary(bin) => { 
@@ -277,8 +393,94 @@ impl StreamHandler<ws::Message, ws::ProtocolError> for MyWebSocket {
 }
 
 impl MyWebSocket {
-    fn new(token: CookieToken, server_addr: Addr<cah_server::CahServer>, match_name: String) -> Self {
-        Self { hb: Instant::now(), cookie_token: token, match_name: match_name, server_addr: server_addr }
+    fn new(token: CookieToken, server_addr: Addr<cah_server::CahServer>, match_name: String, spectating: bool) -> Self {
+        Self { hb: Instant::now(), cookie_token: token, match_name, spectating, protocol_version: None, server_addr }
+    }
+
+    /// Check the protocol version of the client, then connect the socket to its match and welcome it
+    fn hello(&mut self, protocol_version: u32, client: &str, last_seq: Option<u64>, request_id: Option<u64>, ctx: &mut <Self as Actor>::Context) {
+        if self.protocol_version.is_some() {
+            MyWebSocket::send_to_client(MatchError::AlreadySaidHello.to_message("hello", request_id), ctx);
+            return;
+        }
+        println!("Client '{}' says hello with protocol version {}", client, protocol_version);
+        if protocol_version < messages::MIN_PROTOCOL_VERSION || protocol_version > messages::PROTOCOL_VERSION {
+            let reason = format!("Protocol version {} is not supported, use {} to {}", protocol_version, messages::MIN_PROTOCOL_VERSION, messages::PROTOCOL_VERSION);
+            MyWebSocket::close_with(CLOSE_CODE_UNSUPPORTED_PROTOCOL, &reason, ctx);
+            return;
+        }
+        self.protocol_version = Some(protocol_version);
+
+        match self.connect_to_match(last_seq, ctx) {
+            Ok(player_id) => {
+                // The game state or the replayed events are on their way through the mailbox, so the welcome still arrives first
+                let welcome = ServerMessage::Welcome{protocol_version: messages::PROTOCOL_VERSION, features: messages::SERVER_FEATURES, player_id};
+                MyWebSocket::send_to_client(welcome, ctx);
+                MyWebSocket::send_ack("hello", request_id, ctx);
+            },
+            Err(err_msg) => {
+                println!("ERROR while connecting websocket: '{}'", err_msg);
+                MyWebSocket::close_with(CLOSE_CODE_CONNECT_FAILED, "Could not connect to the match", ctx);
+            },
+        }
+    }
+
+    // @arg last_seq see `ClientMessage::Hello`
+    // @return the id of our player, None for spectators
+    fn connect_to_match(&mut self, last_seq: Option<u64>, ctx: &mut <Self as Actor>::Context) -> Result<Option<PlayerId>, String> {
+        let addr = ctx.address();
+        if self.spectating {
+            return match self.server_addr.send(incomming::SpectateMatch{match_name: self.match_name.clone(), addr, last_seq}).wait() {
+                Ok(Ok(())) => Ok(None),
+                Ok(Err(err_msg)) => Err(err_msg),
+                Err(mailbox_err) => Err(mailbox_err.to_string()),
+            };
+        }
+
+        match self.server_addr.send(incomming::SocketConnectMatch{addr, token: self.cookie_token, last_seq}).wait() {
+            Ok(Ok(player_id)) => Ok(Some(player_id)),
+            Ok(Err(err_msg)) => Err(err_msg),
+            Err(mailbox_err) => Err(mailbox_err.to_string()),
+        }
+    }
+
+    // Close the socket with one of the `CLOSE_CODE_*` codes, the reason has to fit in a close frame
+    fn close_with(code: u16, reason: &str, ctx: &mut <Self as Actor>::Context) {
+        ctx.close(Some(ws::CloseReason{code: ws::CloseCode::Other(code), description: Some(reason.to_string())}));
+        ctx.stop();
+    }
+
+    /// Send a message to this client only
+    fn send_to_client(msg: ServerMessage, ctx: &mut <Self as Actor>::Context) {
+        ctx.text(messages::outgoing::Message::from(msg).0);
+    }
+
+    /// Acknowledge an action which went through, clients which did not give it a `request_id` don't wait for this
+    fn send_ack(action: &str, request_id: Option<u64>, ctx: &mut <Self as Actor>::Context) {
+        if let Some(request_id) = request_id {
+            MyWebSocket::send_to_client(ServerMessage::Ack{action: action.to_string(), request_id}, ctx);
+        }
+    }
+
+    /// Forward an action of this player to the `CahServer`, the client receives an `ack` when it went through or an `error` when it got rejected.
+    fn send_match_action<M>(&self, action: &'static str, request_id: Option<u64>, msg: M, ctx: &mut <Self as Actor>::Context)
+    where
+        M: actix::Message<Result = Result<(), MatchError>> + Send + 'static,
+        cah_server::CahServer: Handler<M>,
+    {
+        self.server_addr.send(msg)
+            .into_actor(self)
+            .map(move |action_result, _act, ctx| {
+                match action_result {
+                    Ok(()) => MyWebSocket::send_ack(action, request_id, ctx),
+                    Err(match_error) => {
+                        println!("Rejected match action: {}", match_error);
+                        MyWebSocket::send_to_client(match_error.to_message(action, request_id), ctx);
+                    },
+                }
+            })
+            .map_err(|mailbox_err, _act, _ctx| println!("ERROR sending match action to the server: {}", mailbox_err))
+            .spawn(ctx);
     }
 
     /// helper method that sends ping to client every second.
@@ -386,7 +588,13 @@ fn get_card_deck(_r: HttpRequest, session: Session, server_address: web::Data<Ad
     }
 }
 
-fn post_add_card(_r: HttpRequest, session: Session, body: web::Payload, server_address: web::Data<Addr<cah_server::CahServer>>, path: web::Path<(String, String)>) -> impl Future<Item=HttpResponse, Error=Error> {
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddCardQuery {
+    /// How many white cards a black card asks for, when left out it's the amount of blanks in the card content
+    pub pick: Option<u32>,
+}
+
+fn post_add_card(_r: HttpRequest, session: Session, body: web::Payload, server_address: web::Data<Addr<cah_server::CahServer>>, path: web::Path<(String, String)>, query: web::Query<AddCardQuery>) -> impl Future<Item=HttpResponse, Error=Error> {
     let cookie_token_result = session.get("ct").map_err(|err| format!("error getting cookie token: {}", err));
     
     web::block::<_, (CookieToken, String, bool), String>(move || {
@@ -419,14 +627,17 @@ fn post_add_card(_r: HttpRequest, session: Session, body: web::Payload, server_a
                 .map_err(|blocking_err| format!("Error while adding card: {}", blocking_err))
             })
     })
-    .and_then(move |(cookie_token, card_deck, card_content, is_black)| server_address.send(messages::incomming::AddCard{token: cookie_token, deck_name: card_deck.clone(), card_content: card_content, is_black: is_black})
+    .and_then(move |(cookie_token, card_deck, card_content, is_black)| {
+        let pick = if is_black { query.pick.unwrap_or_else(|| cah_server::Card::count_blanks(&card_content)) } else { 1 };
+        server_address.send(messages::incomming::AddCard{token: cookie_token, deck_name: card_deck.clone(), card_content, is_black, pick})
         .map_err(|mailbox_err| format!("Error adding card in mailbox: {}", mailbox_err))
         .map( |card_id_result| {
             match card_id_result {
                 Ok(card_id) => HttpResponse::Ok().body(format!("{}", card_id)),
                 Err(error_message) =>  HttpResponse::build(StatusCode::BAD_REQUEST).body(error_message)
             }
-        }))
+        })
+    })
         //TODO: Return the actual error
     .map_err( |proper_error| { println!("error while trying to add card: {}", proper_error); Error::from(()) } ) 
 }
@@ -482,9 +693,13 @@ fn main() -> io::Result<()> {
             .service(web::resource("/ws/{match}").route(web::get().to(ws_index)))
             .service( web::scope("/api/")
                 .service(web::resource("/list_matches").route(web::get().to_async(get_list_rooms)))
+                .service(web::resource("/matches")
+                    .route(web::get().to_async(get_list_rooms))
+                    .route(web::post().to_async(post_create_match)))
+                .service(web::resource("/matches/{match}").route(web::delete().to_async(delete_match)))
                 .service(web::resource("/login").route(web::post().to_async(post_page_login)))
                 .service(web::resource("/register").route(web::post().to_async(post_page_register)))
-                .service(web::resource("/join/{match}").route(web::get().to(get_join_match))) 
+                .service(web::resource("/join/{match}").route(web::post().to(post_join_match)))
                 .service(web::resource("/cards/{card_deck}").route(web::get().to_async(get_card_deck)))
                 .service(web::resource("/add/{type}/{card_deck}").route(web::post().to_async(post_add_card)))
                 .service(web::resource("/del/{card_deck}/{card_id}").route(web::post().to_async(post_del_card)))
@@ -1,26 +1,83 @@
-use crate::cah_server::{Card, CardId, CardDeck, PlayerId, Player, GameState};
+use crate::cah_server::{Card, CardId, CardDeck, PlayerId, Player, GameState, SubmissionId, TeamId, TeamHand, Score, ChatEntry, MatchPhase, MatchSettings, MatchInfo, MatchError};
 use crate::CookieToken;
 use actix::prelude::*;
 use std::string::String;
 use crate::MyWebSocket;
 
+/// The version of the websocket protocol in `incomming::ClientMessage` and `outgoing::ServerMessage`,
+/// bumped whenever a change would break clients built for an older version
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The oldest protocol version clients can still say hello with
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// What this server can do, so clients can hide what it can't
+pub const SERVER_FEATURES: &[&str] = &["pickN", "timers", "spectating", "waitlist", "inviteCodes", "bots", "houseRules", "blankCards", "teams", "replay", "chat"];
+
 // Containing all messages which will be commin in from a client to the server
 pub mod incomming {
     use crate::messages::*;
 
+    /// Everything a client can send over its websocket, `type` says which one it is.
+    /// The match and the player are known from the socket, so they are never part of the message.
+    /// Any message can carry a numeric `request_id`, the server answers it with an `Ack` or an `Error` carrying the same id
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "camelCase")]
+    pub enum ClientMessage {
+        // Has to be the first message, the server answers with a `Welcome` or closes the socket
+        Hello {
+            protocol_version: u32,
+            // Which client this is, only used for logging
+            #[serde(default)]
+            client: String,
+            // The `seq` of the last event received before the socket was lost, the server replays everything after it.
+            // Without it, or when too much was missed, the client gets a new game state instead
+            #[serde(default)]
+            last_seq: Option<u64>,
+        },
+        SubmitCard {
+            card_ids: Vec<CardId>,
+            // Only needed when blank cards are submitted
+            #[serde(default)]
+            write_ins: Vec<String>,
+        },
+        StartGame,
+        LeaveMatch,
+        Rematch,
+        KickPlayer { player_id: PlayerId },
+        TransferHost { player_id: PlayerId },
+        AddBot { strategy: String },
+        LockMatch { locked: bool },
+        ChangeSettings { settings: MatchSettings },
+        RevealCard { submission_id: SubmissionId },
+        CzarChoice { submission_id: SubmissionId },
+        EliminateSubmission { submission_id: SubmissionId },
+        Vote { submission_id: SubmissionId },
+        JoinTeam { team: TeamId },
+        TeamChat { text: String },
+        Chat { text: String },
+        RebootHand,
+        ConfessCard { card_id: CardId },
+    }
+
     /// When a socket connection has been established and the socket wants to be bound to a match
     pub struct SocketConnectMatch {
         pub addr: Addr<MyWebSocket>,
         pub token: CookieToken,
+        // The seq of the last event a reconnecting client received, see `ClientMessage::Hello`
+        pub last_seq: Option<u64>,
     }
     impl actix::Message for SocketConnectMatch {
-        type Result = Result<(), String>;
+        /// The id of the player the socket belongs to
+        type Result = Result<PlayerId, String>;
     }
 
     // A request from a client to join a match
     pub struct JoinMatch {
         pub match_name: String,
         pub token: CookieToken,
+        // Needed for matches with a password
+        pub password: Option<String>,
+        // Needed for invite only matches
+        pub invite_code: Option<String>,
     }
     impl actix::Message for JoinMatch {
         type Result = Result<GameState, String>;
@@ -32,11 +89,38 @@ pub mod incomming {
         pub token: CookieToken,
     }
 
+    /// A socket wants to watch a match without playing, no login is needed for this
+    pub struct SpectateMatch {
+        pub match_name: String,
+        pub addr: Addr<MyWebSocket>,
+        // The seq of the last event a reconnecting client received, see `ClientMessage::Hello`
+        pub last_seq: Option<u64>,
+    }
+    impl actix::Message for SpectateMatch {
+        type Result = Result<(), String>;
+    }
+
+    #[derive(Message)]
+    pub struct StopSpectating {
+        pub match_name: String,
+        pub addr: Addr<MyWebSocket>,
+    }
+
+    /// The socket of a player closed, the player stays in the match so they can reconnect
     #[derive(Message)]
+    pub struct SocketDisconnect {
+        pub match_name: String,
+        pub token: CookieToken,
+        pub addr: Addr<MyWebSocket>,
+    }
+
     pub struct RevealCard {
         pub token: CookieToken,
         pub match_name: String,
-        pub card_id: CardId,
+        pub submission_id: SubmissionId,
+    }
+    impl actix::Message for RevealCard {
+        type Result = Result<(), MatchError>;
     }
 
     // #[derive(Message)]
@@ -60,40 +144,193 @@ pub mod incomming {
         type Result = Result<CookieToken, String>;
     }
 
-    /// Disconnect from everything
-    #[derive(Message)]
-    pub struct Disconnect {
-        pub token: CookieToken,
-    }
-
     /// List of available rooms request, this doesn't need to be over a websocket actually.
     #[derive(Default)]
     pub struct ListRooms{
         pub cookie_token: CookieToken,
     }
     impl actix::Message for ListRooms {
-        type Result = Vec<String>;
+        type Result = Vec<MatchInfo>;
+    }
+
+    /// Create a new match, the player creating it becomes the host
+    pub struct CreateMatch {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub settings: MatchSettings,
+        pub password: Option<String>,
+        pub invite_only: bool,
+    }
+    impl actix::Message for CreateMatch {
+        /// The invite code for invite only matches
+        type Result = Result<Option<String>, String>;
+    }
+
+    /// Remove a match, only allowed for the host
+    pub struct DeleteMatch {
+        pub token: CookieToken,
+        pub match_name: String,
+    }
+    impl actix::Message for DeleteMatch {
+        type Result = Result<(), String>;
     }
 
-    #[derive(Message)]
     pub struct SubmitCard {
         pub token: CookieToken,
-        pub card_id: CardId,
+        // In the order they should fill the blanks of the black card
+        pub card_ids: Vec<CardId>,
+        // The answers written on the blank cards among `card_ids`, in the same order
+        pub write_ins: Vec<String>,
         // pub card_content: String,
     }
+    impl actix::Message for SubmitCard {
+        type Result = Result<(), MatchError>;
+    }
 
-    #[derive(Message)]
     pub struct StartMatch {
         pub token: CookieToken,
         pub match_name: String,
     }
+    impl actix::Message for StartMatch {
+        type Result = Result<(), MatchError>;
+    }
 
-    #[derive(Message)]
-    pub struct CzarChoice {
+    /// Start the match again with the same players once someone has won, only allowed for the host
+    pub struct Rematch {
+        pub token: CookieToken,
+        pub match_name: String,
+    }
+    impl actix::Message for Rematch {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Remove a player from the match for good, only allowed for the host
+    pub struct KickPlayer {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub player_id: PlayerId,
+    }
+    impl actix::Message for KickPlayer {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Seat a bot playing with the named strategy (see `bots::STRATEGY_NAMES`), only allowed for the host
+    pub struct AddBot {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub strategy: String,
+    }
+    impl actix::Message for AddBot {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Throw out a submission with the Survival of the Fittest house rule, only allowed on our turn
+    pub struct EliminateSubmission {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub submission_id: SubmissionId,
+    }
+    impl actix::Message for EliminateSubmission {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Switch to another team, only before the match starts
+    pub struct JoinTeam {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub team: TeamId,
+    }
+    impl actix::Message for JoinTeam {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Send a message to everyone in the match chat
+    pub struct Chat {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub text: String,
+    }
+    impl actix::Message for Chat {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Send a message only our own team can read
+    pub struct TeamChat {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub text: String,
+    }
+    impl actix::Message for TeamChat {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Vote for someone else's submission with the God Is Dead house rule
+    pub struct Vote {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub submission_id: SubmissionId,
+    }
+    impl actix::Message for Vote {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Trade in a point for a new hand with the Rebooting the Universe house rule
+    pub struct RebootHand {
+        pub token: CookieToken,
+        pub match_name: String,
+    }
+    impl actix::Message for RebootHand {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Swap a card nobody should have to explain for a new one, with the Never Have I Ever house rule
+    pub struct ConfessCard {
         pub token: CookieToken,
         pub match_name: String,
         pub card_id: CardId,
     }
+    impl actix::Message for ConfessCard {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Make another player in the match the host, only allowed for the host
+    pub struct TransferHost {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub player_id: PlayerId,
+    }
+    impl actix::Message for TransferHost {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Stop or allow new players joining the match, only allowed for the host
+    pub struct LockMatch {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub locked: bool,
+    }
+    impl actix::Message for LockMatch {
+        type Result = Result<(), MatchError>;
+    }
+
+    /// Replace the settings of a match which hasn't started yet, only allowed for the host
+    pub struct ChangeSettings {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub settings: MatchSettings,
+    }
+    impl actix::Message for ChangeSettings {
+        type Result = Result<(), MatchError>;
+    }
+
+    pub struct CzarChoice {
+        pub token: CookieToken,
+        pub match_name: String,
+        pub submission_id: SubmissionId,
+    }
+    impl actix::Message for CzarChoice {
+        type Result = Result<(), MatchError>;
+    }
 
     pub struct GetCards {
         pub token: CookieToken,
@@ -108,6 +345,7 @@ pub mod incomming {
         pub deck_name: String,
         pub card_content: String,
         pub is_black: bool,
+        pub pick: u32,
     }
     impl actix::Message for AddCard {
         type Result = Result<CardId, String>;
@@ -130,6 +368,76 @@ pub mod outgoing {
     /// Chat server sends this messages to session
     #[derive(Message, Clone)]
     pub struct Message(pub String);
+    impl Message {
+        /// A match event numbered with `seq`, see `Match::send_event`
+        pub fn sequenced(seq: u64, msg: &ServerMessage) -> Self {
+            let mut message_json = serde_json::to_value(msg).expect("Every server message can be serialized");
+            message_json["seq"] = seq.into();
+            Message(message_json.to_string())
+        }
+    }
+    impl From<ServerMessage> for Message {
+        fn from(msg: ServerMessage) -> Self {
+            Message(serde_json::to_string(&msg).expect("Every server message can be serialized"))
+        }
+    }
+
+    /// Everything the server can send over a websocket, `type` says which one it is.
+    /// A few types are snake_case, because clients already know them by that name
+    #[derive(Serialize)]
+    #[serde(tag = "type", rename_all = "camelCase")]
+    pub enum ServerMessage {
+        // The answer to a `Hello`, `player_id` is None for spectators
+        Welcome { protocol_version: u32, features: &'static [&'static str], player_id: Option<PlayerId> },
+        // Everything a new or reconnecting client needs to catch up
+        GameState(Box<GameState>),
+        #[serde(rename = "player_joined")]
+        PlayerJoined { player: Player },
+        #[serde(rename = "player_left")]
+        PlayerLeft { player_id: PlayerId },
+        WaitlistPosition { position: usize },
+        NewHost { host: PlayerId },
+        Kicked,
+        MatchLocked { locked: bool },
+        SettingsChanged { settings: MatchSettings },
+        MatchDeleted,
+        MatchStarted,
+        Rematch,
+        NewRound,
+        NewPhase { phase: MatchPhase, deadline: Option<u64> },
+        NewCzar { czar: PlayerId },
+        NewBlack { card_id: CardId, card_content: String, pick: u32 },
+        AddCardToHand { card_id: CardId, card_content: String },
+        RemoveCard { card_id: CardId },
+        SubmitAccepted { card_ids: Vec<CardId> },
+        #[serde(rename = "everyone_submitted")]
+        EveryoneSubmitted { submission_ids: Vec<SubmissionId>, pick: u32 },
+        RevealCard { submission_id: SubmissionId, cards: Vec<Card> },
+        #[serde(rename = "czar_choice")]
+        CzarChoice { submission_id: SubmissionId, cards: Vec<Card> },
+        CzarTimedOut { czar: PlayerId },
+        // With Survival of the Fittest, the player didn't eliminate a submission in time so a random one was thrown out for them
+        EliminationTimedOut { player_id: PlayerId },
+        RoundWon { player_id: PlayerId, team: Option<TeamId> },
+        FinalRound,
+        GameOver { winner: PlayerId, winning_team: Option<TeamId>, scoreboard: Vec<Score> },
+        EliminationTurn { player_id: PlayerId },
+        SubmissionEliminated { submission_id: SubmissionId, player_id: PlayerId },
+        HandRebooted { player_id: PlayerId, points: u32 },
+        CardConfessed { player_id: PlayerId, card: Card },
+        VoteCast { player_id: PlayerId },
+        VotesTallied { votes: Vec<u32> },
+        TeamChanged { player_id: PlayerId, team: TeamId },
+        TeamHands { hands: Vec<TeamHand> },
+        TeamSubmitted { player_id: PlayerId, cards: Vec<Card> },
+        TeamMessage { player_id: PlayerId, text: String },
+        // A message in the match chat, everyone in the match sees it
+        Chat(ChatEntry),
+        // An action of this client went through, only sent when the client gave it a `request_id`
+        Ack { action: String, request_id: u64 },
+        // An action of this client got rejected, see `MatchError`. `request_id` is the one the client sent along, if any
+        Error { action: String, request_id: Option<u64>, error: &'static str, message: String },
+    }
 
     #[derive(Message)]
     pub struct AddCardToHand {
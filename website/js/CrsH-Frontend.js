@@ -7,13 +7,15 @@
 
 
 // Variables:
-//type: HashMap<cardElement, cardId
+//type: HashMap<submissionId, cardElement>
 var revealedCardIdToElement = {};
 
 //type: UIElement
 var selectedCard = null;
 //type: number
 var selectedCardId = null;
+//type: Array<cardId>, the cards from our hand selected to be submitted, in the order they fill the blanks
+var selectedCardIds = [];
 
 //type HashMap<cardId, cardContent>
 var cardIdToContent = {};
@@ -25,6 +27,7 @@ $(document).ready(function() {
 	$("#cardRevealing").hide();
 
 	//Connect listeners to the gameplay events:
+	connection.onDisconnected.add(onDisconnected);
 	connection.onGameState.add(onNewGameStateReceived);
 	connection.onPlayerJoinedMatch.add(onPlayerJoined);
 	connection.onPlayerLeftMatch.add(onPlayerLeft);
@@ -32,12 +35,36 @@ $(document).ready(function() {
 	connection.onRevealCard.add(onRevealOthersCard);
 	connection.onCzarCardChoice.add(onCzarCardChoiceReceived);
 	connection.onNewRound.add(onNewRoundStarted);
-	connection.onPlayerWon.add(onPlayerWon);
+	connection.onGameOver.add(onGameOver);
+	connection.onRematch.add(onRematch);
 	connection.onPlayerRoundWin.add(onPlayerRoundWin);
 	connection.onNewCzar.add(onNewCzar);
+	connection.onNewHost.add(renderUserList);
+	connection.onMatchDeleted.add(onMatchDeleted);
 	connection.onMatchHasStarted.add(onMatchHasStarted);
 	connection.onRemoveCardFromHand.add(onRemoveCardFromHand);
 	connection.onNewBlackCard.add(onNewBlackCard);
+	connection.onMatchError.add(onMatchError);
+	connection.onCzarTimedOut.add(onCzarTimedOut);
+	connection.onEliminationTimedOut.add(onEliminationTimedOut);
+	connection.onKicked.add(onKicked);
+	connection.onMatchLocked.add(renderUserList);
+	connection.onSettingsChanged.add(renderUserList);
+	connection.onWaitlistPosition.add(renderUserList);
+	connection.onFinalRound.add(onFinalRound);
+	connection.onEliminationTurn.add(renderUserList);
+	connection.onSubmissionEliminated.add(onSubmissionEliminated);
+	connection.onVoteCast.add(renderUserList);
+	connection.onVotesTallied.add(onVotesTallied);
+	connection.onHandRebooted.add(onHandRebooted);
+	connection.onCardConfessed.add(onCardConfessed);
+	connection.onTeamChanged.add(renderUserList);
+	connection.onTeamHands.add(renderTeamHands);
+	connection.onTeamSubmitted.add(onTeamSubmitted);
+	connection.onTeamMessage.add(onTeamMessage);
+	connection.onChat.add(appendChat);
+
+	setInterval(renderCountdown, 500);
 
 	//Create forms which don't redirect you to another page:
     $('#loginForm').ajaxForm({
@@ -59,7 +86,46 @@ $(document).ready(function() {
 });
 
 function onNewGameStateReceived(msg) {
+	playerPoints = {};
+	$.each(msg.scores, function(i, score) {
+		playerPoints[score.player.id] = score.points;
+	});
+
+	if(msg.blackCard != null) {
+		onNewBlackCard(new incommingMessages.NewBlackCard(msg.blackCard.id, msg.blackCard.content, msg.blackCard.pick));
+	}
+
+	selectedCardIds = [];
+	renderHandOfCards();
+	if(msg.submissions.length > 0) {
+		onEveryoneSubmittedCards(new incommingMessages.EveryoneSubmittedCards($.map(msg.submissions, function(submission) { return submission.submission_id; }), pickCount));
+		$.each(msg.submissions, function(i, submission) {
+			if(submission.cards != null) {
+				onRevealOthersCard(new incommingMessages.RevealCard(submission.submission_id, submission.cards));
+			}
+			if(submission.eliminated) {
+				onSubmissionEliminated(new incommingMessages.SubmissionEliminated(submission.submission_id, 0));
+			}
+		});
+	} else {
+		$("#cardRevealing").hide();
+		$("#handOfCards").show();
+	}
+
+	var canSubmit = matchPhase == "submitting" ? !isCzar() && !hasSubmittedCard : (matchPhase == "revealing" || matchPhase == "judging") && isCzar() && !isHouseRuleOn("survival_of_the_fittest");
+	canSubmit = canSubmit && !isSpectating && waitlistPosition == null;
+	$("#submitButton").attr("disabled", !canSubmit);
+	$("#rematchButton").prop('disabled', !(isHost() && matchPhase == "game_over"));
 	renderUserList();
+	renderTeamHands();
+	renderChat();
+}
+function onDisconnected(msg) {
+	if(msg.code == CLOSE_CODE_UNSUPPORTED_PROTOCOL) {
+		alert("This page is too old for the server, reload it to keep playing.\n\n" + msg.reason);
+	} else if(msg.code == CLOSE_CODE_HELLO_TIMEOUT || msg.code == CLOSE_CODE_CONNECT_FAILED) {
+		alert("The server closed the connection: " + msg.reason);
+	}
 }
 function onPlayerJoined(msg) {
 	renderUserList();
@@ -69,16 +135,118 @@ function onPlayerLeft(msg) {
 }
 
 function renderUserList(){
-	var isCzar = userList.length > 0 && userList[0].id == ourSelves.id;
-	$("#startGameButton").prop('disabled', !isCzar);
+	$("#startGameButton").prop('disabled', !isHost());
+	$("#lockMatchButton").prop('disabled', !isHost()).text(isMatchLocked ? "Unlock match" : "Lock match");
+	$("#changeSettingsButton").prop('disabled', !(isHost() && matchPhase == "lobby"));
+	$("#addBotButton").prop('disabled', !(isHost() && matchPhase == "lobby"));
+	$("#rebootHandButton").toggle(isHouseRuleOn("rebooting_the_universe")).prop('disabled', !(matchPhase == "submitting" && !hasSubmittedCard && ourSelves != null && playerPoints[ourSelves.id] > 0));
+	$("#confessCardButton").toggle(isHouseRuleOn("never_have_i_ever")).prop('disabled', !(matchPhase == "submitting" && !hasSubmittedCard));
+	$("#finalRound").text(isFinalRound ? "Final round: make a haiku!" : "");
+	var houseRulesText = matchSettings != null ? Object.keys(matchSettings.house_rules).filter(function(houseRule) { return matchSettings.house_rules[houseRule] === true; }).join(", ") : "";
+	$("#matchSettings").text(matchSettings != null ? matchSettings.points_to_win + " points to win, hand size: " + matchSettings.hand_size + ", decks: " + matchSettings.active_decks.join(", ") + (houseRulesText != "" ? ", house rules: " + houseRulesText : "") + (isHouseRuleOn("god_is_dead") ? ", vote ties: " + matchSettings.house_rules.vote_tiebreak : "") + (matchSettings.blank_cards > 0 ? ", blank cards: " + matchSettings.blank_cards : "") + (isTeamPlay() ? ", teams: " + matchSettings.teams : "") : "");
+	renderTeams();
+	$("#inviteCode").text(inviteCode != null ? "Invite code: " + inviteCode : "");
+	$("#waitlistPosition").text(waitlistPosition != null ? "The match is full, you are number " + waitlistPosition + " on the waitlist" : "");
 
 	$("#userList").html('');
 	$.each(userList, function(i, val) {
 		var czarString = val.id == czarId ? " (czar)" : "";
-		var opString = i == 0 ? " (op)" : "";
+		var opString = val.id == hostId ? " (op)" : "";
+		var botString = isBot(val.id) ? " (bot)" : "";
+		var eliminatingString = val.id == eliminatingPlayerId ? " (eliminating)" : "";
+		var votedString = hasVoted(val.id) ? " (voted)" : "";
+		var teamString = isTeamPlay() && playerTeams[val.id] != null ? " [Team " + (playerTeams[val.id] + 1) + "]" : "";
+
+		$("#userList").append(document.createTextNode(val.name + czarString + opString + botString + eliminatingString + votedString + teamString + "    Points => " + playerPoints[val.id] + " "));
+		// Rando Cardrissian is a house rule, so it is turned off in the settings instead
+		if(isHost() && val.id != ourSelves.id && val.id != RANDO_CARDRISSIAN_ID) {
+			var playerId = val.id;
+			$("#userList").append($('<button/>').text('Kick').click(function() { kickPlayer(playerId); }));
+			if(!isBot(playerId)) {
+				$("#userList").append($('<button/>').text('Make host').click(function() { transferHost(playerId); }));
+			}
+		}
+		$("#userList").append("<br>");
+	});
+}
+
+function renderTeams() {
+	$("#joinTeamArea").toggle(isTeamPlay() && matchPhase == "lobby");
+	$("#teamArea").toggle(isTeamPlay());
+	if(!isTeamPlay()) {
+		return;
+	}
+
+	var ourTeam = ourSelves != null ? playerTeams[ourSelves.id] : null;
+	if($("#joinTeamField option").length != matchSettings.teams) {
+		$("#joinTeamField").html('');
+		for(var team = 0; team < matchSettings.teams; team++) {
+			$("#joinTeamField").append($('<option/>').val(team).text("Team " + (team + 1)));
+		}
+	}
+	$("#teamScores").text((ourTeam != null ? "Team " + (ourTeam + 1) : "none") + ", points: " + $.map(teamPoints, function(points, team) {
+		return "Team " + (team + 1) + " => " + points;
+	}).join(", "));
+}
+
+function renderTeamHands() {
+	$("#teamHands").html('');
+	$.each(teamHands, function(i, hand) {
+		if(ourSelves != null && hand.player_id == ourSelves.id) {
+			return;
+		}
+		var player = userList.find(function(player) {
+			return player.id == hand.player_id;
+		});
+		var cardsText = $.map(hand.cards, function(card) {
+			return card.content === "" ? "(blank card)" : card.content;
+		}).join(" | ");
+		$("#teamHands").append(document.createTextNode((player != null ? player.name : "Someone") + ": " + cardsText));
+		$("#teamHands").append("<br>");
+	});
+}
+
+function renderChat() {
+	$("#chatLog").html('');
+	$.each(chatHistory, function(i, msg) {
+		appendChat(msg);
+	});
+}
+
+function appendChat(msg) {
+	var line = new Date(msg.sentAt).toLocaleTimeString() + " ";
+	if(msg.playerId == null) {
+		line += "* " + msg.text;
+	} else {
+		var player = userList.find(function(player) {
+			return player.id == msg.playerId;
+		});
+		line += (player != null ? player.name : "Someone") + ": " + msg.text;
+	}
+	$("#chatLog").append(document.createTextNode(line));
+	$("#chatLog").append("<br>");
+	$("#chatLog").scrollTop($("#chatLog")[0].scrollHeight);
+}
+
+function appendTeamChat(text) {
+	$("#teamChatLog").append(document.createTextNode(text));
+	$("#teamChatLog").append("<br>");
+}
+
+function onTeamSubmitted(msg) {
+	$("#submitButton").attr("disabled", true);
+	var player = userList.find(function(player) {
+		return player.id == msg.playerId;
+	});
+	appendTeamChat((player != null ? player.name : "Someone") + " played for the team: " + $.map(msg.cards, function(card) { return card.content; }).join(" | "));
+	renderUserList();
+}
 
-		$("#userList").append(val.name + czarString + opString + "    Points => " + playerPoints[val.id] + "<br>");
+function onTeamMessage(msg) {
+	var player = userList.find(function(player) {
+		return player.id == msg.playerId;
 	});
+	appendTeamChat((player != null ? player.name : "Someone") + ": " + msg.text);
 }
 
 function onNewRoundStarted() {
@@ -89,6 +257,8 @@ function onNewRoundStarted() {
 	$(".selectedCard").removeClass("selectedCard");
 	selectedCard = null;
 	selectedCardId = null;
+	selectedCardIds = [];
+	renderHandOfCards();
 
 	if(isCzar()) {
 		$("#submitButton").attr("disabled", true);
@@ -100,6 +270,9 @@ function onNewRoundStarted() {
 function onNewBlackCard(msg) {
 	var cardContent = msg.cardContent;
 
+	if(msg.pick > 1) {
+		cardContent += " (Pick " + msg.pick + ")";
+	}
 	$("#blackCard").text(cardContent);
 }
 
@@ -109,6 +282,7 @@ function onMatchHasStarted() {
 	} else {
 		$("#submitButton").attr("disabled", false);
 	}
+	renderUserList();
 }
 
 function onPlayerRoundWin(msg) {
@@ -123,13 +297,48 @@ function onPlayerRoundWin(msg) {
 	renderUserList();
 }
 
-function onPlayerWon(msg) {
-	var playerId = msg.playerId;
+function onHandRebooted(msg) {
+	playerPoints[msg.playerId] = msg.points;
+	renderUserList();
+}
 
-	var playerName = userList.find(function(nameIdPair, i) {
-		return nameIdPair.id == playerId;
-	})
-	alert("player with the name: " + playerName.name + " has won the match!");
+function onCardConfessed(msg) {
+	var player = userList.find(function(player) {
+		return player.id == msg.playerId;
+	});
+	alert((player != null ? player.name : "Someone") + " confessed to not understanding: " + msg.card.content);
+}
+
+function onFinalRound() {
+	alert("We have a winner! But first everyone gets to make a haiku.");
+	renderUserList();
+}
+
+function onGameOver(msg) {
+	var scoreboardText = $.map(msg.scoreboard, function(score, i) {
+		playerPoints[score.player.id] = score.points;
+		return (i + 1) + ". " + score.player.name + " => " + score.points;
+	}).join("\n");
+	renderUserList();
+
+	var winner = msg.scoreboard.find(function(score) {
+		return score.player.id == msg.playerId;
+	});
+	var winnerName = winner != null ? winner.player.name : "Someone who left";
+	if(msg.winningTeam != null) {
+		winnerName = "Team " + (msg.winningTeam + 1) + " of " + winnerName;
+	}
+	alert("player with the name: " + winnerName + " has won the match!\n\n" + scoreboardText);
+
+	$("#rematchButton").prop('disabled', !isHost());
+}
+
+function onRematch() {
+	playerPoints = {};
+	$("#rematchButton").prop('disabled', true);
+
+	onNewRoundStarted();
+	renderUserList();
 }
 
 function onNewCzar(msg) {
@@ -143,7 +352,7 @@ function onNewCzar(msg) {
 }
 
 function onEveryoneSubmittedCards(msg) {
-	var ids = msg.cardIds;
+	var ids = msg.submissionIds;
 
 	$("#handOfCards").hide();
 	$("#cardRevealing").show();
@@ -172,20 +381,24 @@ function onEveryoneSubmittedCards(msg) {
 }
 
 function onRevealOthersCard(msg) {
-	var id = msg.cardId;
-	var content = msg.cardContent;
+	var id = msg.submissionId;
+	var content = $.map(msg.cards, function(card) { return card.content; }).join(" / ");
 
 	revealedCardIdToElement[id].classList.remove("downfacingCard");
 	revealedCardIdToElement[id].classList.add("revealedCard");
 	revealedCardIdToElement[id].innerText = content;
 
 	revealedCardIdToElement[id].onclick = function() {
-		let everythingRevealed = false;
-		$.each(everyonesSubmittedCards, function(i, val){
-			everythingRevealed |= val != null;
-		});
-		
-		if(everythingRevealed) {
+		if(matchPhase == "judging" && isHouseRuleOn("survival_of_the_fittest")) {
+			if(isOurEliminationTurn() && eliminatedSubmissions.indexOf(id) === -1) {
+				eliminateSubmission(id);
+			}
+		} else if(matchPhase == "judging" && isHouseRuleOn("god_is_dead")) {
+			// The server refuses votes for our own submission
+			if(ourSelves != null && !hasVoted(ourSelves.id)) {
+				vote(id);
+			}
+		} else if(matchPhase == "judging") {
 			deselectCards();
 			selectCard(this);
 			selectedCard = this;
@@ -197,13 +410,30 @@ function onRevealOthersCard(msg) {
 	
 }
 
+function onSubmissionEliminated(msg) {
+	var cardElem = revealedCardIdToElement[msg.submissionId];
+	if(cardElem != null) {
+		cardElem.classList.add("eliminatedCard");
+	}
+	renderUserList();
+}
+
+function onVotesTallied(msg) {
+	$.each(msg.votes, function(submissionId, votes) {
+		var cardElem = revealedCardIdToElement[submissionId];
+		if(cardElem != null) {
+			cardElem.innerText += " (" + votes + " votes)";
+		}
+	});
+}
+
 function onCzarCardChoiceReceived(msg) {
-	var cardId = msg.cardId;
-	var cardElem = revealedCardIdToElement[cardId];
+	var submissionId = msg.submissionId;
+	var cardElem = revealedCardIdToElement[submissionId];
 	if(cardElem != null) {
 		cardElem.classList.add("chosenCard");
 	}else{
-		console.error("ERROR: Cannot find a card with the id: " + cardId);
+		console.error("ERROR: Cannot find a submission with the id: " + submissionId);
 	}
 }
 
@@ -246,30 +476,52 @@ function submitSelection() {
 			return;
 		}
 
-		var selectedCard = getSelectedCard();
-		if (selectedCard === null) {
-			alert("No card is selected! please select one by clicking on it!");
+		if (selectedCardIds.length != pickCount) {
+			alert("Please select " + pickCount + " card(s) by clicking on them, in the order they should fill the blanks!");
 		} else {
-			var cardId = selectedCardId;
-			if(cardId == null) {
-				console.error("Selected card: ", selectCard, "Doesn't have an id");
-				return;
+			var writeIns = [];
+			var blankCardIds = selectedCardIds.filter(isBlankCard);
+			for (var i = 0; i < blankCardIds.length; i++) {
+				var writeIn = prompt("Write your answer on the blank card:");
+				if (writeIn == null || writeIn.trim() == "") {
+					return;
+				}
+				writeIns.push(writeIn);
 			}
-			selectedCard.classList.add("submittedCard");
+
+			$(".selectedCard").addClass("submittedCard");
 			$("#submitButton").attr("disabled",  true);
 
-			submitCard(cardId);
+			submitCard(selectedCardIds.slice(), writeIns);
 		}
 	}
 }
 
 // Called from a html button
 function startGame() {
-    if (connection != null) {
+    // Clicking again before the server answered would only get us an error
+    if (connection != null && !connection.isPending("startGame")) {
         connection.sendStartGame();
     }
 }
 
+// Called from a html button
+function leaveMatch() {
+    if (connection != null && connection.isConnected()) {
+        connection.sendLeaveMatch();
+        userList = [];
+        renderUserList();
+        refreshMatchList();
+    }
+}
+
+// Called from a html button
+function startRematch() {
+    if (connection != null) {
+        connection.sendRematch();
+    }
+}
+
 function deselectCards() {
 	var whiteCards = document.getElementsByClassName("whiteCard");
 	for (var i = 0; i < whiteCards.length; i++) {
@@ -296,22 +548,36 @@ function renderHandOfCards() {
 
 	$.each(handOfCards, function(i, val) {
 		var cardId = val;
-		var text = cardIdToContent[cardId];
+		var text = isBlankCard(cardId) ? "(blank card, write your own answer)" : cardIdToContent[cardId];
 
 		/* Create a card like this:
 		<div class="whiteCard">The text of the card</div>
 		*/
 		var card = document.createElement('div');
 		card.classList.add("whiteCard");
+		var selectionIndex = selectedCardIds.indexOf(cardId);
+		if (selectionIndex !== -1) {
+			selectCard(card);
+			if (pickCount > 1) {
+				text = "(" + (selectionIndex + 1) + ") " + text;
+			}
+		}
 		var textNode = document.createTextNode(text);
 		card.appendChild(textNode);
 
 		card.onclick = function () {
 			if (!hasSubmittedCard) {
-				deselectCards();
-				selectCard(this);
-				selectedCard = this;
-				selectedCardId = cardId;
+				var index = selectedCardIds.indexOf(cardId);
+				if (index !== -1) {
+					selectedCardIds.splice(index, 1);
+				} else {
+					// Drop the oldest selection when we already picked enough cards
+					if (selectedCardIds.length >= pickCount) {
+						selectedCardIds.shift();
+					}
+					selectedCardIds.push(cardId);
+				}
+				renderHandOfCards();
 			}
 		}
 
@@ -326,6 +592,11 @@ function onRemoveCardFromHand(msg) {
 	renderHandOfCards();
 }
 
+// Blank cards are the only white cards without content, the player writes their own answer on them
+function isBlankCard(cardId) {
+	return cardIdToContent[cardId] === "";
+}
+
 //overload gameplay callbacks to visualize it.
 gameplayCallbacks.addWhiteCard = function(msg) {
 	var text = msg.cardContent;
@@ -336,25 +607,208 @@ gameplayCallbacks.addWhiteCard = function(msg) {
 	renderHandOfCards();
 };
 
+function onMatchDeleted() {
+	alert("The match has been deleted by the host.");
+	renderUserList();
+	refreshMatchList();
+}
+
+function renderCountdown() {
+	if(phaseDeadline == null) {
+		$("#countdown").text("");
+		return;
+	}
+
+	var secondsLeft = Math.max(0, Math.ceil((phaseDeadline - Date.now()) / 1000));
+	$("#countdown").text(secondsLeft + " seconds left");
+}
+
+function onKicked() {
+	alert("You have been kicked from the match by the host.");
+	renderUserList();
+	refreshMatchList();
+}
+
+function onCzarTimedOut(msg) {
+	var czar = userList.find(function(player) {
+		return player.id == msg.czar;
+	});
+	alert((czar != null ? czar.name : "The czar") + " didn't pick a winner in time, the round is skipped.");
+}
+
+function onEliminationTimedOut(msg) {
+	var player = userList.find(function(player) {
+		return player.id == msg.playerId;
+	});
+	alert((player != null ? player.name : "Someone") + " didn't eliminate a submission in time, a random one was thrown out.");
+}
+
+function onMatchError(msg) {
+	console.error("The server rejected our action '" + msg.action + "' (" + msg.error + "): " + msg.message);
+
+	if(msg.action == "submitCard" || msg.action == "czarChoice") {
+		$(".submittedCard").removeClass("submittedCard");
+		$("#submitButton").attr("disabled",  false);
+	}
+	alert(msg.message);
+}
+
+// The match settings filled in the create match form
+function readMatchSettingsForm() {
+	return {
+		active_decks: $("#createMatchDecksField").val().split(",").map(function(deck) { return deck.trim(); }),
+		points_to_win: parseInt($("#createMatchPointsField").val()),
+		hand_size: parseInt($("#createMatchHandSizeField").val()),
+		max_players: parseInt($("#createMatchMaxPlayersField").val()),
+		submit_seconds: parseInt($("#createMatchSubmitSecondsField").val()),
+		judge_seconds: parseInt($("#createMatchJudgeSecondsField").val()),
+		blank_cards: parseInt($("#createMatchBlankCardsField").val()),
+		write_in_deck: $("#createMatchWriteInDeckField").val().trim() != "" ? $("#createMatchWriteInDeckField").val().trim() : null,
+		teams: parseInt($("#createMatchTeamsField").val()),
+		house_rules: {
+			rando_cardrissian: $("#createMatchRandoField").prop('checked'),
+			happy_ending: $("#createMatchHappyEndingField").prop('checked'),
+			rebooting_the_universe: $("#createMatchRebootingField").prop('checked'),
+			packing_heat: $("#createMatchPackingHeatField").prop('checked'),
+			never_have_i_ever: $("#createMatchNeverHaveIEverField").prop('checked'),
+			survival_of_the_fittest: $("#createMatchSurvivalField").prop('checked'),
+			god_is_dead: $("#createMatchGodIsDeadField").prop('checked'),
+			vote_tiebreak: $("#createMatchVoteTiebreakField").val(),
+		},
+	};
+}
+
+// Called from a html button
+function rebootOurHand() {
+	if (connection != null && connection.isConnected()) {
+		rebootHand();
+	}
+}
+
+// Called from a html button
+function joinSelectedTeam() {
+	if (connection != null && connection.isConnected()) {
+		joinTeam(parseInt($("#joinTeamField").val()));
+	}
+}
+
+// Called from a html button
+function sendMatchChatMessage() {
+	var text = $("#chatField").val().trim();
+	if (text != "" && connection != null && connection.isConnected()) {
+		sendChatMessage(text);
+		$("#chatField").val('');
+	}
+}
+
+// Called from a html button
+function sendTeamChatMessage() {
+	var text = $("#teamChatField").val().trim();
+	if (text != "" && connection != null && connection.isConnected()) {
+		sendTeamMessage(text);
+		$("#teamChatField").val('');
+	}
+}
+
+// Called from a html button
+function confessSelectedCard() {
+	if (selectedCardIds.length != 1) {
+		alert("Select the one card you don't understand by clicking on it!");
+	} else if (connection != null && connection.isConnected()) {
+		confessCard(selectedCardIds[0]);
+		selectedCardIds = [];
+	}
+}
+
+// Called from a html button
+function addBotToMatch() {
+	if (connection != null && connection.isConnected()) {
+		addBot($("#botStrategyField").val());
+	}
+}
+
+// Called from a html button
+function toggleMatchLocked() {
+	if (connection != null && connection.isConnected()) {
+		setMatchLocked(!isMatchLocked);
+	}
+}
+
+// Called from a html button
+function applyMatchSettings() {
+	if (connection != null && connection.isConnected()) {
+		changeMatchSettings(readMatchSettingsForm());
+	}
+}
+
+// Called from a html button
+function createMatch() {
+	var settings = readMatchSettingsForm();
+
+	var inviteOnly = $("#createMatchInviteOnlyField").prop('checked');
+	sendCreateMatch(new outgoingMessages.CreateMatch($("#createMatchNameField").val(), settings, $("#createMatchPasswordField").val(), inviteOnly))
+		.done(function(data) {
+			if(inviteOnly) {
+				alert("Others can join your match with the invite code: " + JSON.parse(data).invite_code);
+			}
+			refreshMatchList();
+		})
+		.fail(function(request, status, error) {
+			alert("ERROR creating match. Some info: " + request.responseText + " + " + error + " + " + status);
+		});
+}
+
+// @arg password OPTIONAL
+// @arg inviteCode OPTIONAL
+function joinMatch(matchId, password, inviteCode) {
+	alert('Joining match: ' + matchId);
+	var ajaxRequest = sendJoinMatch(new outgoingMessages.JoinMatch(matchId, password, inviteCode));
+	ajaxRequest.done(function(data) {
+		console.log("Successfully joined match!");
+		//TODO: Set some html element to a happy face or something idk
+		connection.connect(matchId);
+	});
+	ajaxRequest.fail(function(request, status, error) {
+		alert("ERROR match memes went wrong!!!. Some info: " + request.responseText + " + " + error + " + " + status);
+	});
+}
+
+// Called from a html button
+function joinWithInviteCode() {
+	joinMatch($("#inviteMatchNameField").val(), undefined, $("#inviteCodeField").val());
+}
+
 gameplayCallbacks.matchListReceived = function(matchList) {
 	$("#matches").html("");
 
-	$.each(matchList, function(i, matchId) { 
+	$.each(matchList, function(i, matchInfo) { 
+		var matchId = matchInfo.name;
 		var idCopy = matchId;
 		var btn = $('<button/>')
 			.text('Join match')
 			.click(function() { 
-				alert('Joining match: ' + idCopy); 
-				var ajaxRequest = sendJoinMatch(new outgoingMessages.JoinMatch(idCopy));
-				ajaxRequest.done(function(data) { 
-					console.log("Successfully joined match!");
-					//TODO: Set some html element to a happy face or something idk
-					connection.connect(idCopy);
-				});
-				ajaxRequest.fail(function(request, status, error) {
-					alert("ERROR match memes went wrong!!!. Some info: " + request.responseText + " + " + error + " + " + status);
-				});
+				// Players already in a private match can rejoin it without the password
+				var password = matchInfo.private ? prompt("The password of match: " + idCopy) : undefined;
+				joinMatch(idCopy, password);
+			});
+		var watchBtn = $('<button/>')
+			.text('Watch')
+			.prop('disabled', matchInfo.private)
+			.click(function() {
+				connection.connect(idCopy, true);
+			});
+		var description = matchId + " (" + matchInfo.player_count + "/" + matchInfo.settings.max_players + " players, " + matchInfo.waitlist_count + " waiting, " + matchInfo.spectator_count + " watching, "
+			+ matchInfo.settings.points_to_win + " points to win, decks: " + matchInfo.settings.active_decks.join(", ")
+			+ (matchInfo.started ? ", in progress" : "") + (matchInfo.locked ? ", locked" : "") + (matchInfo.private ? ", private" : "") + ") ";
+		var deleteBtn = $('<button/>')
+			.text('Delete')
+			.click(function() {
+				sendDeleteMatch(new outgoingMessages.DeleteMatch(idCopy))
+					.done(refreshMatchList)
+					.fail(function(request, status, error) {
+						alert("ERROR deleting match. Some info: " + request.responseText + " + " + error + " + " + status);
+					});
 			});
-		$("#matches").append(matchId).append(btn).append("<br>");
+		$("#matches").append(document.createTextNode(description)).append(btn).append(watchBtn).append(deleteBtn).append("<br>");
 	});
 }
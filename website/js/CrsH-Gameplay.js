@@ -5,7 +5,7 @@
  * \dependson `CrsH-ServerAPI.js` `jquery-3.4.0.js` `signals.js`
  */
 
-//type: Array<matchId>
+//type: Array<{name, host, player_count, spectator_count, waitlist_count, started, locked, private, settings}>
 var matchList = [];
 //type Array<cardId>
 var handOfCards = [];
@@ -15,8 +15,44 @@ var userList = [];
 var ourSelves = null;
 //type: string
 var czarId = null;
-//type: HashMap<cardId, cardContent>
+//type: number
+var hostId = null;
+//type: HashMap<submissionId, Array<{content, id}>>
 var everyonesSubmittedCards = {};
+//type: number, the amount of white cards the current black card asks for
+var pickCount = 1;
+//type: string, the phase the match is in, see `incommingMessages.NewPhase`
+var matchPhase = "lobby";
+//type: number|null, when the time of the current phase runs out in milliseconds since the unix epoch
+var phaseDeadline = null;
+//type: bool, true if we are only watching the match
+var isSpectating = false;
+//type: bool, true if no new players can join the match
+var isMatchLocked = false;
+//type: {active_decks, points_to_win, hand_size, max_players, submit_seconds, judge_seconds, blank_cards, write_in_deck, teams, house_rules: {rando_cardrissian, happy_ending, rebooting_the_universe, packing_heat, never_have_i_ever, survival_of_the_fittest, god_is_dead, vote_tiebreak}}
+var matchSettings = null;
+//type: Array<String>, what the server we are connected to can do, see `SERVER_FEATURES` in `messages.rs`
+var serverFeatures = [];
+//type: string|null, the code others need to join our invite only match
+var inviteCode = null;
+//type: number|null, our place on the waitlist of a full match, null when we are a player
+var waitlistPosition = null;
+//type: bool, true during the "Make a haiku" round of the Happy Ending house rule
+var isFinalRound = false;
+//type: number, whose turn it is to eliminate a submission with the Survival of the Fittest house rule, 0 if nobody
+var eliminatingPlayerId = 0;
+//type: Array<submissionId>, the submissions thrown out this round
+var eliminatedSubmissions = [];
+//type: Array<playerId>, who already voted this round with the God Is Dead house rule
+var votedPlayers = [];
+//type: {playerId: team}, the team of every player when the match is played in teams
+var playerTeams = {};
+//type: Array<number>, the points of every team, indexed by team
+var teamPoints = [];
+//type: Array<{player_id, cards}>, the hands of everyone in our team, including our own
+var teamHands = [];
+//type: Array<{playerId, text, sentAt}>, the latest messages of the match chat, oldest first
+var chatHistory = [];
 
 var hasSubmittedCard = false;
 var everyoneHasSubmittedCards = false;
@@ -25,6 +61,9 @@ var hasSubmittedCzarChoice = false;
 //type: class ServerSocketConnection
 var connection = null;
 
+// The player id of the house rule bot playing random cards, bots added by the host have lower ids
+var RANDO_CARDRISSIAN_ID = -1;
+
 
 //TODO: When I feel like it make this the matchListReceived callback use the actual incommingMessage type. 
 
@@ -33,16 +72,18 @@ var gameplayCallbacks = {
 	// signature: function(msg: incommingMessages.AddCardToHand)
 	addWhiteCard: null,
 
-	// signature: function(matchList: Array<matchId>)
+	// signature: function(matchList: Array<{name, host, player_count, spectator_count, waitlist_count, started, locked, private, settings}>)
 	matchListReceived: null,
 
 	// signature: function()
 	matchHasStarted: null
 }
 
-function submitCard(cardId) {
+// @arg cardIds Array<cardId> in the order they fill the blanks of the black card
+// @arg writeIns Array<String> the answers for the blank cards among `cardIds`, in the same order
+function submitCard(cardIds, writeIns) {
 	if(!hasSubmittedCard) {
-		connection.sendSubmitCard(new outgoingMessages.SubmitCard(cardId));	
+		connection.sendSubmitCard(new outgoingMessages.SubmitCard(cardIds, writeIns));	
 
 		hasSubmittedCard = true;
 	}
@@ -50,9 +91,66 @@ function submitCard(cardId) {
 function isCzar() {
 	return ourSelves != null && ourSelves.id == czarId;
 }
+function isHost() {
+	return ourSelves != null && ourSelves.id == hostId;
+}
 function czarChooseCard(cardId) {
 
 }
+function kickPlayer(playerId) {
+	connection.sendKickPlayer(new outgoingMessages.KickPlayer(playerId));
+}
+function isHouseRuleOn(houseRule) {
+	return matchSettings != null && matchSettings.house_rules[houseRule];
+}
+function isOurEliminationTurn() {
+	return ourSelves != null && ourSelves.id == eliminatingPlayerId;
+}
+function eliminateSubmission(submissionId) {
+	connection.sendEliminateSubmission(new outgoingMessages.EliminateSubmission(submissionId));
+}
+function hasServerFeature(feature) {
+	return serverFeatures.includes(feature);
+}
+function isTeamPlay() {
+	return matchSettings != null && matchSettings.teams > 0;
+}
+function joinTeam(team) {
+	connection.sendJoinTeam(new outgoingMessages.JoinTeam(team));
+}
+function sendChatMessage(text) {
+	connection.sendChat(new outgoingMessages.Chat(text));
+}
+function sendTeamMessage(text) {
+	connection.sendTeamChat(new outgoingMessages.TeamChat(text));
+}
+function hasVoted(playerId) {
+	return votedPlayers.indexOf(playerId) !== -1;
+}
+function vote(submissionId) {
+	connection.sendVote(new outgoingMessages.Vote(submissionId));
+}
+function rebootHand() {
+	connection.sendRebootHand(new outgoingMessages.RebootHand());
+}
+function confessCard(cardId) {
+	connection.sendConfessCard(new outgoingMessages.ConfessCard(cardId));
+}
+function isBot(playerId) {
+	return playerId < 0;
+}
+function addBot(strategy) {
+	connection.sendAddBot(new outgoingMessages.AddBot(strategy));
+}
+function transferHost(playerId) {
+	connection.sendTransferHost(new outgoingMessages.TransferHost(playerId));
+}
+function setMatchLocked(locked) {
+	connection.sendLockMatch(new outgoingMessages.LockMatch(locked));
+}
+function changeMatchSettings(settings) {
+	connection.sendChangeSettings(new outgoingMessages.ChangeSettings(settings));
+}
 
 function refreshMatchList() {
 	var ajaxReq = sendListMatches();
@@ -97,11 +195,52 @@ function _newGameStateReceived(gameStateMessage) {
 	ourSelves = gameStateMessage.ourPlayer;
 
 	czarId = gameStateMessage.czar;
+	hostId = gameStateMessage.host;
+	matchPhase = gameStateMessage.phase;
+	phaseDeadline = gameStateMessage.deadline;
+	isSpectating = gameStateMessage.spectating;
+	isMatchLocked = gameStateMessage.locked;
+	matchSettings = gameStateMessage.settings;
+	inviteCode = gameStateMessage.inviteCode;
+	waitlistPosition = gameStateMessage.waitlistPosition;
+	isFinalRound = gameStateMessage.finalRound;
+	eliminatingPlayerId = gameStateMessage.eliminatingPlayer;
+	votedPlayers = gameStateMessage.votedPlayers;
+	teamHands = gameStateMessage.teamHands;
+	chatHistory = $.map(gameStateMessage.chat, function(entry) {
+		return new incommingMessages.Chat(entry.player_id, entry.text, entry.sent_at);
+	});
+	playerTeams = {};
+	teamPoints = [];
+	$.each(gameStateMessage.teams, function(i, team) {
+		teamPoints[team.team] = team.points;
+		$.each(team.players, function(j, playerId) {
+			playerTeams[playerId] = team.team;
+		});
+	});
 
 	userList = [];
 	$.each(gameStateMessage.otherPlayers, function(i, val) {
 		userList.push(val);
 	});
+
+	handOfCards = [];
+	$.each(gameStateMessage.handOfCards, function(i, card) {
+		_addWhiteCard(new incommingMessages.AddCardToHand(card.content, card.id));
+	});
+
+	pickCount = gameStateMessage.blackCard != null ? gameStateMessage.blackCard.pick : 1;
+	hasSubmittedCard = gameStateMessage.submittedCards.length > 0;
+	hasSubmittedCzarChoice = false;
+	everyoneHasSubmittedCards = gameStateMessage.submissions.length > 0;
+	everyonesSubmittedCards = {};
+	eliminatedSubmissions = [];
+	$.each(gameStateMessage.submissions, function(i, submission) {
+		everyonesSubmittedCards[submission.submission_id] = submission.cards;
+		if(submission.eliminated) {
+			eliminatedSubmissions.push(submission.submission_id);
+		}
+	});
 }
 
 function _newCzar(msg) {
@@ -110,6 +249,84 @@ function _newCzar(msg) {
 	czarId = czar;
 }
 
+function _newHost(msg) {
+	hostId = msg.host;
+}
+
+function _matchDeleted() {
+	connection.disconnect();
+	userList = [];
+	handOfCards = [];
+	resetMatch();
+}
+
+function _kicked() {
+	// Same as the match being gone for us, we can't join it again
+	_matchDeleted();
+}
+
+function _matchLocked(msg) {
+	isMatchLocked = msg.locked;
+}
+
+function _settingsChanged(msg) {
+	if(matchSettings == null || matchSettings.teams != msg.settings.teams) {
+		// The server deals everyone into new teams, `TeamChanged` follows
+		teamPoints = [];
+		for(var team = 0; team < msg.settings.teams; team++) {
+			teamPoints[team] = 0;
+		}
+	}
+	matchSettings = msg.settings;
+}
+
+function _waitlistPosition(msg) {
+	waitlistPosition = msg.position;
+}
+
+function _finalRound() {
+	isFinalRound = true;
+}
+
+function _eliminationTurn(msg) {
+	eliminatingPlayerId = msg.playerId;
+}
+
+function _submissionEliminated(msg) {
+	eliminatedSubmissions.push(msg.submissionId);
+}
+
+function _welcome(msg) {
+	serverFeatures = msg.features;
+}
+
+function _voteCast(msg) {
+	votedPlayers.push(msg.playerId);
+}
+
+function _teamChanged(msg) {
+	playerTeams[msg.playerId] = msg.team;
+}
+
+function _chat(msg) {
+	chatHistory.push(msg);
+}
+
+function _teamHands(msg) {
+	teamHands = msg.hands;
+}
+
+function _teamSubmitted(msg) {
+	// The team only submits once, so it doesn't matter who did
+	hasSubmittedCard = true;
+}
+
+function _teamScored(msg) {
+	if(msg.team != null) {
+		teamPoints[msg.team] = (teamPoints[msg.team] || 0) + 1;
+	}
+}
+
 function _playerLeft(message) {
 	var index = -1;
 	$.each(userList, function(i, val) {
@@ -133,7 +350,7 @@ function _matchHasStarted() {
 }
 
 function _everyoneSubmittedCards(msg) {
-	var ids = msg.cardIds;
+	var ids = msg.submissionIds;
 
 	everyoneHasSubmittedCards = true;
 
@@ -144,14 +361,29 @@ function _everyoneSubmittedCards(msg) {
 }
 
 function _revealOthersCard(msg) {
-	var id = msg.cardId;
-	var content = msg.cardContent;
+	var id = msg.submissionId;
 
 	if(everyonesSubmittedCards[id] == null) {
-		everyonesSubmittedCards[id] = content;
+		everyonesSubmittedCards[id] = msg.cards;
 	}
 }
 
+function _newBlackCard(msg) {
+	pickCount = msg.pick;
+}
+
+function _matchError(msg) {
+	// Our cards didn't make it, so we are allowed to try again
+	if(msg.action == "submitCard") {
+		hasSubmittedCard = false;
+	}
+}
+
+function _newPhase(msg) {
+	matchPhase = msg.phase;
+	phaseDeadline = msg.deadline;
+}
+
 function _czarCardChoiceReceived(msg) {
 
 }
@@ -159,6 +391,7 @@ function _czarCardChoiceReceived(msg) {
 $(document).ready(function () {
 	connection = new ServerSocketConnection();
 
+	connection.onWelcome.add(_welcome);
 	connection.onAddCardToHand.add(_addWhiteCard);
 	connection.onGameState.add(_newGameStateReceived);
 	connection.onPlayerLeftMatch.add(_playerLeft);
@@ -168,18 +401,47 @@ $(document).ready(function () {
 	connection.onRevealCard.add(_revealOthersCard);
 	connection.onCzarCardChoice.add(_czarCardChoiceReceived);
 	connection.onNewRound.add(_newRoundStarts);
-	connection.onPlayerWon.add(_playerHasWon);
+	connection.onGameOver.add(_playerHasWon);
+	connection.onRematch.add(_rematch);
 	connection.onNewCzar.add(_newCzar);
+	connection.onNewHost.add(_newHost);
+	connection.onMatchDeleted.add(_matchDeleted);
+	connection.onKicked.add(_kicked);
+	connection.onMatchLocked.add(_matchLocked);
+	connection.onSettingsChanged.add(_settingsChanged);
+	connection.onWaitlistPosition.add(_waitlistPosition);
+	connection.onFinalRound.add(_finalRound);
+	connection.onEliminationTurn.add(_eliminationTurn);
+	connection.onSubmissionEliminated.add(_submissionEliminated);
+	connection.onVoteCast.add(_voteCast);
+	connection.onTeamChanged.add(_teamChanged);
+	connection.onTeamHands.add(_teamHands);
+	connection.onChat.add(_chat);
+	connection.onTeamSubmitted.add(_teamSubmitted);
+	connection.onPlayerRoundWin.add(_teamScored);
 	connection.onRemoveCardFromHand.add(_removeWhiteCard);
+	connection.onNewBlackCard.add(_newBlackCard);
+	connection.onNewPhase.add(_newPhase);
+	connection.onMatchError.add(_matchError);
 });
 
 function _playerHasWon(msg) {
 	resetMatch();
 }
 
+function _rematch() {
+	handOfCards = [];
+	isFinalRound = false;
+	teamPoints = teamPoints.map(function() { return 0; });
+	_newRoundStarts();
+}
+
 function _newRoundStarts() {
 	hasSubmittedCard = false;
 	everyoneHasSubmittedCards = false;
 	hasSubmittedCzarChoice = false;
 	everyonesSubmittedCards = {};
+	eliminatingPlayerId = 0;
+	eliminatedSubmissions = [];
+	votedPlayers = [];
 }
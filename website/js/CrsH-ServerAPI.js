@@ -6,31 +6,139 @@
  */
 
 
+// The version of the websocket protocol this client speaks, the server closes the socket if it can't speak it
+var PROTOCOL_VERSION = 1;
+// The codes the server closes the websocket with, see `CLOSE_CODE_*` in `main.rs`
+var CLOSE_CODE_UNSUPPORTED_PROTOCOL = 4000;
+var CLOSE_CODE_HELLO_TIMEOUT = 4001;
+var CLOSE_CODE_CONNECT_FAILED = 4002;
+// How long to wait before connecting again when the socket got lost, and how often to try
+var RECONNECT_DELAY_MS = 2000;
+var MAX_RECONNECT_ATTEMPTS = 5;
+
 // Message Types for messages which can be send from the client
 var outgoingMessages = {
-	// @arg cardId the cardId from your hand to submit for this round
-	SubmitCard: function(cardId) {
-		this.cardId = cardId;
+	// Send by `ServerSocketConnection` itself as soon as the socket opens
+	// @arg lastSeq OPTIONAL the `seq` of the last event we received when reconnecting, the server replays what we missed
+	Hello: function(protocolVersion, client, lastSeq) {
+		this.protocolVersion = protocolVersion;
+		this.client = client;
+		this.lastSeq = lastSeq;
 	},
-	// @arg cardId the card czar reveals a card. 
-	RevealCard: function(cardId) {
-		this.cardId = cardId;
+	// @arg cardIds Array<cardId> the cards from your hand to submit for this round, in the order they fill the blanks of the black card
+	// @arg writeIns Array<String> the answers written on the blank cards among `cardIds`, in the same order. Empty without blank cards
+	SubmitCard: function(cardIds, writeIns) {
+		this.cardIds = cardIds;
+		this.writeIns = writeIns;
+	},
+	// @arg submissionId the submission the czar reveals.
+	RevealCard: function(submissionId) {
+		this.submissionId = submissionId;
 	},
-	JoinMatch: function(matchId) {
+	// @arg password OPTIONAL needed to join a match with a password
+	// @arg inviteCode OPTIONAL needed to join an invite only match
+	JoinMatch: function(matchId, password, inviteCode) {
 		this.matchId = matchId;
+		this.password = password;
+		this.inviteCode = inviteCode;
 	},
 	ListMatches: function() {
 	},
+	// @arg matchName the name of the new match
+	// @arg settings {active_decks, points_to_win, hand_size, max_players, submit_seconds, judge_seconds, blank_cards, write_in_deck, teams, house_rules: {rando_cardrissian, happy_ending, rebooting_the_universe, packing_heat, never_have_i_ever, survival_of_the_fittest, god_is_dead, vote_tiebreak}} every field is optional and falls back to the server defaults
+	// @arg password OPTIONAL makes the match private, only players who know the password can join
+	// @arg inviteOnly OPTIONAL makes the match private and hides it from the match list, the server responds with an invite code
+	CreateMatch: function(matchName, settings, password, inviteOnly) {
+		this.matchName = matchName;
+		this.settings = settings;
+		this.password = password;
+		this.inviteOnly = inviteOnly;
+	},
+	DeleteMatch: function(matchId) {
+		this.matchId = matchId;
+	},
 	StartMatch: function() {
 	},
-	// @arg cardId the card id of the card which is the best
-	CzarCardChoice: function(cardId) {
+	// @arg submissionId the id of the submission which is the best
+	CzarCardChoice: function(submissionId) {
+		this.submissionId = submissionId;
+	},
+	// Only the host can kick players, a kicked player can't join the match again
+	// @arg playerId the id of the player to kick
+	KickPlayer: function(playerId) {
+		this.playerId = playerId;
+	},
+	// Only the host can add bots, and only before the match has started. Bots are removed again by kicking them
+	// @arg strategy one of "random", "keyword" or "longestAnswer"
+	AddBot: function(strategy) {
+		this.strategy = strategy;
+	},
+	// Only with the Survival of the Fittest house rule, on our turn
+	// @arg submissionId the submission to throw out
+	EliminateSubmission: function(submissionId) {
+		this.submissionId = submissionId;
+	},
+	// Only in a match played in teams, and only before it starts
+	// @arg team the number of the team, from 0 up to the `teams` setting
+	JoinTeam: function(team) {
+		this.team = team;
+	},
+	// Only in a match played in teams, everyone in our team gets a `TeamMessage`
+	// @arg text what we want to say
+	TeamChat: function(text) {
+		this.text = text;
+	},
+	// Everyone in the match gets a `Chat`, a few messages every couple of seconds at most
+	// @arg text what we want to say
+	Chat: function(text) {
+		this.text = text;
+	},
+	// Only with the God Is Dead house rule, once every round and not for our own submission
+	// @arg submissionId the submission we like best
+	Vote: function(submissionId) {
+		this.submissionId = submissionId;
+	},
+	// Only with the Rebooting the Universe house rule, trades in one of our points for a new hand
+	RebootHand: function() {
+	},
+	// Only with the Never Have I Ever house rule, everyone gets to see the card we swap
+	// @arg cardId the card from our hand we don't understand
+	ConfessCard: function(cardId) {
 		this.cardId = cardId;
+	},
+	// @arg playerId the id of the player who becomes the new host
+	TransferHost: function(playerId) {
+		this.playerId = playerId;
+	},
+	// @arg locked Boolean when true no new players can join the match
+	LockMatch: function(locked) {
+		this.locked = locked;
+	},
+	// Only possible before the match has started
+	// @arg settings see `outgoingMessages.CreateMatch`
+	ChangeSettings: function(settings) {
+		this.settings = settings;
 	}
 };
 
 // Message Types for messages which are received by the client
 var incommingMessages = {
+	// The answer to our `Hello`, nothing else is received before it
+	// @arg protocolVersion the newest protocol version the server speaks
+	// @arg features Array<String> what the server can do, like "pickN", "timers" or "teams"
+	// @arg playerId Number|null the id of our player, null when spectating
+	Welcome: function(protocolVersion, features, playerId) {
+		this.protocolVersion = protocolVersion;
+		this.features = features;
+		this.playerId = playerId;
+	},
+	// Not send by the server, but dispatched when the socket closes
+	// @arg code the close code, one of the `CLOSE_CODE_*` values when the server refused us
+	// @arg reason why the socket was closed, can be empty
+	Disconnected: function(code, reason) {
+		this.code = code;
+		this.reason = reason;
+	},
 	AddCardToHand: function(cardContent, cardId) {
 		this.cardContent = cardContent;
 		this.cardId = cardId;
@@ -43,7 +151,7 @@ var incommingMessages = {
 		this.userUuid = userUuid;
 		this.cardId = cardId;
 	},
-	// @arg matches is List<Pair<MatchId, String>>
+	// @arg matches is Array<{name, host, player_count, spectator_count, waitlist_count, started, locked, private, settings}>
 	ListMatches: function(matches) {
 		this.matches = matches;
 	},
@@ -52,26 +160,64 @@ var incommingMessages = {
 		this.playerId = playerId;
 		this.playerName = playerName;
 	},
-	// @arg cardIds a array of card ids, of all cards submitted this round
-	EveryoneSubmittedCards: function(cardIds) {
-		this.cardIds = cardIds;
+	// @arg submissionIds a array of submission ids, one for every player that submitted this round
+	// @arg pick the amount of cards in every submission
+	EveryoneSubmittedCards: function(submissionIds, pick) {
+		this.submissionIds = submissionIds;
+		this.pick = pick;
 	},
-	// @arg cardContent the text of the white card which gets revealed.
-	// @arg cardId the card the czar revealed. 
-	RevealCard: function(cardContent, cardId) {
-		this.cardContent = cardContent;
-		this.cardId = cardId;
+	// @arg submissionId the submission the czar revealed.
+	// @arg cards Array<{content, id}> the white cards of the submission, in the order they fill the blanks.
+	RevealCard: function(submissionId, cards) {
+		this.submissionId = submissionId;
+		this.cards = cards;
 	},
 	// @arg otherPlayers Array<{name, id}> an array of objects, each object will have a field "name" and "id"
 	// @arg ourPlayer {name, id} an object with the fields "name" and "id"
 	// @arg handOfCards Array<String> an array of cards contents
-	// @arg czar Number a id of the player which is the czar (aka host)
-	GameState: function(otherPlayers, ourPlayer, handOfCards, czar, gameStarted) {
+	// @arg czar Number a id of the player which is the czar
+	// @arg host Number a id of the player which is the host, the only one allowed to start the match
+	// @arg phase String the phase the match is in, see `incommingMessages.NewPhase`
+	// @arg deadline Number|null see `incommingMessages.NewPhase`
+	// @arg blackCard {content, id, pick}|null the black card of the current round
+	// @arg submittedCards Array<{content, id}> the cards we submitted this round, empty if we didn't submit yet
+	// @arg scores Array<{player: {name, id}, points}> the points of every player, the highest score first
+	// @arg submissions Array<{submission_id, cards, eliminated}> the submissions of this round, `cards` is null while it isn't revealed
+	// @arg spectating Boolean true if we are only watching the match, `ourPlayer` is empty then
+	// @arg locked Boolean true if no new players can join the match
+	// @arg settings {active_decks, points_to_win, hand_size, max_players, submit_seconds, judge_seconds, blank_cards, write_in_deck, teams, house_rules: {rando_cardrissian, happy_ending, rebooting_the_universe, packing_heat, never_have_i_ever, survival_of_the_fittest, god_is_dead, vote_tiebreak}} the settings of the match
+	// @arg inviteCode String|null the code others need to join an invite only match
+	// @arg waitlistPosition Number|null our place on the waitlist of a full match, 1 is next in line. null once we are a player
+	// @arg finalRound Boolean true during the "Make a haiku" round of the Happy Ending house rule
+	// @arg eliminatingPlayer Number the id of the player whose turn it is to eliminate a submission, 0 if nobody
+	// @arg votedPlayers Array<Number> the ids of the players who already voted this round with the God Is Dead house rule
+	// @arg teams Array<{team, players, points}> every team with the ids of its players, empty without teams
+	// @arg teamHands Array<{player_id, cards}> the hands of everyone in our team, including our own
+	// @arg chat Array<{player_id, text, sent_at}> the latest messages of the match chat, oldest first
+	GameState: function(otherPlayers, ourPlayer, handOfCards, czar, host, gameStarted, phase, deadline, blackCard, submittedCards, scores, submissions, spectating, locked, settings, inviteCode, waitlistPosition, finalRound, eliminatingPlayer, votedPlayers, teams, teamHands, chat) {
 		this.otherPlayers = otherPlayers;
 		this.ourPlayer = ourPlayer;
 		this.handOfCards = handOfCards;
 		this.czar = czar;
+		this.host = host;
 		this.gameStarted = gameStarted;
+		this.locked = locked;
+		this.settings = settings;
+		this.inviteCode = inviteCode;
+		this.waitlistPosition = waitlistPosition;
+		this.finalRound = finalRound;
+		this.eliminatingPlayer = eliminatingPlayer;
+		this.votedPlayers = votedPlayers;
+		this.teams = teams;
+		this.teamHands = teamHands;
+		this.chat = chat;
+		this.phase = phase;
+		this.deadline = deadline;
+		this.blackCard = blackCard;
+		this.submittedCards = submittedCards;
+		this.scores = scores;
+		this.submissions = submissions;
+		this.spectating = spectating;
 	},
 	// @arg otherPlayer {name, id} an object with the fields "name" and "id"
 	PlayerJoinedMatch: function(otherPlayer) {
@@ -84,16 +230,27 @@ var incommingMessages = {
 	// Fired when the server has agreed that the match has started
 	MatchHasStarted: function() {
 	},
-	// @arg cardId the card id of the card which is the best
-	CzarCardChoice: function(cardId) {
-		this.cardId = cardId;
+	// @arg submissionId the id of the submission which is the best
+	// @arg cards Array<{content, id}> the cards of the winning submission, blank cards carry their write-in as content
+	CzarCardChoice: function(submissionId, cards) {
+		this.submissionId = submissionId;
+		this.cards = cards;
 	},
 	// @arg playerId the playerId of the player who won the match
-	PlayerWon: function(playerId) {
+	// @arg scoreboard Array<{player: {name, id}, points}> every player in the match, the highest score first
+	// @arg winningTeam Number|null the team of the winner, null without teams
+	GameOver: function(playerId, scoreboard, winningTeam) {
 		this.playerId = playerId;
+		this.scoreboard = scoreboard;
+		this.winningTeam = winningTeam;
+	},
+	// The host started a new game with the same players, all points and hands are gone
+	Rematch: function() {
 	},
-	PlayerRoundWin: function(playerId) {
+	// @arg team Number|null the team which gets the point, null without teams
+	PlayerRoundWin: function(playerId, team) {
 		this.playerId = playerId;
+		this.team = team;
 	},
 	// A new round has started
 	NewRound: function() {
@@ -102,6 +259,97 @@ var incommingMessages = {
 	NewCzar: function(czar) {
 		this.czar = czar;
 	},
+	// @arg host the id of the player who is now the host
+	NewHost: function(host) {
+		this.host = host;
+	},
+	// The match we are in has been deleted by the host
+	MatchDeleted: function() {
+	},
+	// The host kicked us out of the match, we can't join it again
+	Kicked: function() {
+	},
+	// @arg locked Boolean true if no new players can join the match anymore
+	MatchLocked: function(locked) {
+		this.locked = locked;
+	},
+	// @arg settings the new settings of the match, see `incommingMessages.GameState`
+	SettingsChanged: function(settings) {
+		this.settings = settings;
+	},
+	// Send to everyone on the waitlist whenever the line moves, once we get a seat a new `GameState` is send instead
+	// @arg position our place on the waitlist, 1 is next in line
+	WaitlistPosition: function(position) {
+		this.position = position;
+	},
+	// Someone reached the points to win, with the Happy Ending house rule one "Make a haiku" round is played before the game is over
+	FinalRound: function() {
+	},
+	// @arg playerId the id of the player who eliminates the next submission
+	EliminationTurn: function(playerId) {
+		this.playerId = playerId;
+	},
+	// @arg submissionId the submission which got thrown out
+	// @arg playerId the id of the player who threw it out
+	SubmissionEliminated: function(submissionId, playerId) {
+		this.submissionId = submissionId;
+		this.playerId = playerId;
+	},
+	// @arg playerId the id of the player who is in another team now
+	// @arg team the team the player is in
+	TeamChanged: function(playerId, team) {
+		this.playerId = playerId;
+		this.team = team;
+	},
+	// Send to everyone in a team whenever the hands are dealt or change
+	// @arg hands Array<{player_id, cards}> the hands of everyone in our team, including our own
+	TeamHands: function(hands) {
+		this.hands = hands;
+	},
+	// Someone in our team submitted the cards of the team for this round
+	// @arg playerId the id of the player who submitted
+	// @arg cards Array<{content, id}> the submitted cards
+	TeamSubmitted: function(playerId, cards) {
+		this.playerId = playerId;
+		this.cards = cards;
+	},
+	// @arg playerId the id of the player in our team who said it
+	// @arg text what they said
+	TeamMessage: function(playerId, text) {
+		this.playerId = playerId;
+		this.text = text;
+	},
+	// A message in the match chat
+	// @arg playerId the id of the player who said it, null when the server tells who joined, left or won the round
+	// @arg text what was said
+	// @arg sentAt when it was said, in milliseconds since the unix epoch
+	Chat: function(playerId, text, sentAt) {
+		this.playerId = playerId;
+		this.text = text;
+		this.sentAt = sentAt;
+	},
+	// Only tells who voted, what they voted for stays secret
+	// @arg playerId the id of the player who voted
+	VoteCast: function(playerId) {
+		this.playerId = playerId;
+	},
+	// Everyone voted or the time ran out, the winners are announced with `PlayerRoundWin` messages afterwards
+	// @arg votes Array<Number> the number of votes of every submission, indexed by the submission id
+	VotesTallied: function(votes) {
+		this.votes = votes;
+	},
+	// @arg playerId the id of the player who traded in a point for a new hand
+	// @arg points the points the player has left
+	HandRebooted: function(playerId, points) {
+		this.playerId = playerId;
+		this.points = points;
+	},
+	// @arg playerId the id of the player who didn't understand the card
+	// @arg card {content, id} the card they swapped for a new one
+	CardConfessed: function(playerId, card) {
+		this.playerId = playerId;
+		this.card = card;
+	},
 	// @arg deckName the name of the deck
 	// @arg blackCards an array of question cards
 	// @arg whiteCards an array of response cards
@@ -110,9 +358,48 @@ var incommingMessages = {
 		this.blackCards = blackCards;
 		this.whiteCards = whiteCards;
 	},
-	NewBlackCard: function(cardId, cardContent) {
+	// @arg pick the amount of white cards which should be submitted for this black card
+	NewBlackCard: function(cardId, cardContent, pick) {
 		this.cardId = cardId;
 		this.cardContent = cardContent;
+		this.pick = pick;
+	},
+	// @arg phase one of: "lobby", "dealing", "submitting", "revealing", "judging", "round_results", "game_over"
+	// @arg deadline Number|null when the time for this phase runs out in milliseconds since the unix epoch, null if there is no time limit
+	NewPhase: function(phase, deadline) {
+		this.phase = phase;
+		this.deadline = deadline;
+	},
+	// @arg czar the id of the czar who didn't pick a submission in time, the round is skipped
+	CzarTimedOut: function(czar) {
+		this.czar = czar;
+	},
+	// @arg playerId the id of the player who didn't eliminate a submission in time with the Survival of the Fittest house rule, a random one was thrown out for them
+	EliminationTimedOut: function(playerId) {
+		this.playerId = playerId;
+	},
+	// Send when the server went through with something we send over the socket
+	// @arg action the type of the message e.g. "startGame"
+	// @arg requestId the id `ServerSocketConnection` gave the message when sending it
+	Ack: function(action, requestId) {
+		this.action = action;
+		this.requestId = requestId;
+	},
+	// Send when the server rejected something we send over the socket
+	// @arg action the type of the message which got rejected e.g. "submitCard"
+	// @arg error a short name of the error e.g. "wrongPhase" or "notCzar"
+	// @arg message a human readable description of the error
+	// @arg requestId the id `ServerSocketConnection` gave the rejected message
+	MatchError: function(action, requestId, error, message) {
+		this.action = action;
+		this.requestId = requestId;
+		this.error = error;
+		this.message = message;
+	},
+	// The server accepted the cards we submitted this round
+	// @arg cardIds Array<cardId> the submitted cards, in the order they fill the blanks
+	SubmitAccepted: function(cardIds) {
+		this.cardIds = cardIds;
 	}
 };
 
@@ -153,13 +440,29 @@ function _parseJsonToGameState(data) {
 		console.error("newGameStateReceived message is a null value! should be a object");
 		return null;
 	}
+
+	return _jsonToGameState(jsonData);
+}
+
+// \returns incommingMessages.GameState if valid, or null if the already parsed json was not a valid GameState.
+function _jsonToGameState(jsonData) {
 	if(!validateJsonProperty(jsonData, 'other_players', 'array', 	"GameState message received,")) { return null; }
 	if(!validateJsonProperty(jsonData, 'our_player', 	'object', 	"GameState message received,")) { return null; }
 	if(!validateJsonProperty(jsonData, 'hand_of_cards', 'array', 	"GameState message received,")) { return null; }
 	if(!validateJsonProperty(jsonData, 'czar', 			'number', 	"GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'host', 			'number', 	"GameState message received,")) { return null; }
 	if(!validateJsonProperty(jsonData, 'started', 		'boolean', 	"GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'phase', 		'string', 	"GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'submitted_cards', 'array', "GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'scores', 		'array', 	"GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'submissions', 	'array', 	"GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'spectating', 	'boolean', 	"GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'locked', 		'boolean', 	"GameState message received,")) { return null; }
+	if(!validateJsonProperty(jsonData, 'settings', 		'object', 	"GameState message received,")) { return null; }
 
-	var message = new incommingMessages.GameState(jsonData["other_players"], jsonData["our_player"], jsonData["hand_of_cards"], jsonData["czar"], jsonData["started"]);
+	var message = new incommingMessages.GameState(jsonData["other_players"], jsonData["our_player"], jsonData["hand_of_cards"], jsonData["czar"], jsonData["host"], jsonData["started"], jsonData["phase"], jsonData["deadline"],
+		jsonData["black_card"], jsonData["submitted_cards"], jsonData["scores"], jsonData["submissions"], jsonData["spectating"], jsonData["locked"], jsonData["settings"], jsonData["invite_code"], jsonData["waitlist_position"],
+		jsonData["final_round"], jsonData["eliminating_player"], jsonData["voted_players"], jsonData["teams"], jsonData["team_hands"], jsonData["chat"]);
 	return message;
 }
 
@@ -185,19 +488,58 @@ function _parseJsonToCardDeck(data) {
 }
 
 // Send a GET request to the server.
-// This function is async, it will return a JQuerry Ajax object. When that request is completed, the data should contain a JSON array of strings
+// This function is async, it will return a JQuerry Ajax object. When that request is completed, the data should contain a JSON array of match infos
 //
-// @returns ajax request returning a String[] of all matches
+// @returns ajax request returning a Array<{name, host, player_count, spectator_count, waitlist_count, started, locked, private, settings}> of all matches
+// Invite only matches are only listed for the players in them
 function sendListMatches() {
 	var req = $.ajax({
-		url: 'api/list_matches',
+		url: 'api/matches',
 		type: 'get',
 	});
 
 	return req;
 }
 
-// send a GET request to join a match. 
+// send a POST request to create a new match, the logged in player becomes the host of it.
+//
+// @arg createMatch an instance of `outgoingMessages.CreateMatch`
+//
+// @returns jquery ajax request object returning a json object {invite_code} for invite only matches on success, but an error string on failure.
+function sendCreateMatch(createMatch) {
+	var payload = $.extend({name: createMatch.matchName}, createMatch.settings);
+	if(createMatch.password) {
+		payload.password = createMatch.password;
+	}
+	if(createMatch.inviteOnly) {
+		payload.invite_only = true;
+	}
+
+	var request = $.ajax({
+		url: '/api/matches',
+		type: 'post',
+		contentType: 'application/json',
+		data: JSON.stringify(payload),
+	});
+
+	return request;
+}
+
+// send a DELETE request to remove a match, only the host of the match is allowed to do this.
+//
+// @arg deleteMatch an instance of `outgoingMessages.DeleteMatch`
+//
+// @returns jquery ajax request object returning nothing on success, but an error string on failure.
+function sendDeleteMatch(deleteMatch) {
+	var request = $.ajax({
+		url: '/api/matches/' + encodeURIComponent(deleteMatch.matchId),
+		type: 'delete',
+	});
+
+	return request;
+}
+
+// send a POST request to join a match, the password and invite code of the match are send as a form in the request body.
 // The server will automiatically disconnect you from a previous match if you were already in another match
 //
 // @arg joinMatch an instance of `outgoingMessages.JoinMatch`
@@ -207,9 +549,19 @@ function sendListMatches() {
 //
 // @returns jquerry ajax request object returning a json object convertable to `incommingMessages.GameState` on success, but an error string on failure
 function sendJoinMatch(joinMatch, afterParsed) {
+	// In the body, so the password doesn't end up in the url
+	var payload = {};
+	if(joinMatch.password) {
+		payload.password = joinMatch.password;
+	}
+	if(joinMatch.inviteCode) {
+		payload.invite = joinMatch.inviteCode;
+	}
+
 	var request = $.ajax({
 		url: '/api/join/' + joinMatch.matchId,
-		type: 'get',
+		type: 'post',
+		data: payload,
 	});
 
 	var afterParsedCopy = afterParsed;
@@ -293,7 +645,19 @@ function sendDeleteCard(deckName, cardId) {
 class ServerSocketConnection {
 	constructor() {
 		this._socketConnection = null;
+		// Where we are connected to, so we can connect there again when the socket gets lost
+		this._matchId = null;
+		this._spectate = false;
+		// The `seq` of the last match event we received, null until we got one
+		this._lastSeq = null;
+		this._reconnectAttempts = 0;
+		// Every message we send gets an id, the server answers it with an ack or an error carrying the same id
+		this._nextRequestId = 1;
+		// The type of every message which wasn't answered yet, by request id
+		this._pendingRequests = {};
 
+		this.onWelcome = new signals.Signal();
+		this.onDisconnected = new signals.Signal();
 		this.onAddCardToHand = new signals.Signal();
 		this.onRemoveCardFromHand = new signals.Signal();
 		this.onPlayerSubmittedCard = new signals.Signal();
@@ -305,42 +669,149 @@ class ServerSocketConnection {
 		// Fired when the server has agreed that the match has started
 		this.onMatchHasStarted = new signals.Signal();
 		this.onCzarCardChoice = new signals.Signal();
-		this.onPlayerWon = new signals.Signal();
+		this.onGameOver = new signals.Signal();
+		this.onRematch = new signals.Signal();
 		this.onNewRound = new signals.Signal();
 		this.onNewCzar = new signals.Signal();
+		this.onNewHost = new signals.Signal();
+		this.onMatchDeleted = new signals.Signal();
+		this.onKicked = new signals.Signal();
+		this.onMatchLocked = new signals.Signal();
+		this.onSettingsChanged = new signals.Signal();
+		this.onWaitlistPosition = new signals.Signal();
+		this.onFinalRound = new signals.Signal();
+		this.onEliminationTurn = new signals.Signal();
+		this.onSubmissionEliminated = new signals.Signal();
+		this.onTeamChanged = new signals.Signal();
+		this.onTeamHands = new signals.Signal();
+		this.onTeamSubmitted = new signals.Signal();
+		this.onTeamMessage = new signals.Signal();
+		this.onChat = new signals.Signal();
+		this.onVoteCast = new signals.Signal();
+		this.onVotesTallied = new signals.Signal();
+		this.onHandRebooted = new signals.Signal();
+		this.onCardConfessed = new signals.Signal();
 		this.onNewBlackCard = new signals.Signal();
 		this.onPlayerRoundWin = new signals.Signal();
+		this.onNewPhase = new signals.Signal();
+		this.onCzarTimedOut = new signals.Signal();
+		this.onEliminationTimedOut = new signals.Signal();
+		this.onAck = new signals.Signal();
+		this.onMatchError = new signals.Signal();
+		this.onSubmitAccepted = new signals.Signal();
 	}
 
 	// @arg submitCard an instance of the type `outgoingMessages.SubmitCard`
 	sendSubmitCard(submitCard) {
-		var message = {type: "submitCard", card_id: submitCard.cardId};
-		var messageJson = JSON.stringify(message);
-
-		this._socketConnection.send(messageJson);
+		var message = {type: "submitCard", card_ids: submitCard.cardIds, write_ins: submitCard.writeIns};
+		return this._sendRequest(message);
 	}
 
 	// @arg czarCardChoice an instance of the type `outgoingMessages.CzarCardChoice`
 	sendCzarCardChoice(czarCardChoice) {
-		var message = {type: "czarChoice", card_id: czarCardChoice.cardId};
-		var messageJson = JSON.stringify(message);
-
-		this._socketConnection.send(messageJson);
+		var message = {type: "czarChoice", submission_id: czarCardChoice.submissionId};
+		return this._sendRequest(message);
 	}
 
 	// @arg revealCard an instance of the type `outgoingMessages.RevealCard`
 	sendRevealCard(revealCard) {
-		var message = {type: "revealCard", card_id: revealCard.cardId};
-		var messageJson = JSON.stringify(message);
-		
-		this._socketConnection.send(messageJson);
+		var message = {type: "revealCard", submission_id: revealCard.submissionId};
+		return this._sendRequest(message);
 	}
 
 	sendStartGame() {
 		var message = {type: "startGame"};
-		var messageJson = JSON.stringify(message);
+		return this._sendRequest(message);
+	}
+
+	// Leave the match for good, closing the socket without this keeps our place in the match
+	sendLeaveMatch() {
+		var message = {type: "leaveMatch"};
+		return this._sendRequest(message);
+	}
+
+	// Only the host can start a rematch, once the match is over
+	sendRematch() {
+		var message = {type: "rematch"};
+		return this._sendRequest(message);
+	}
+
+	// @arg kickPlayer an instance of the type `outgoingMessages.KickPlayer`
+	sendKickPlayer(kickPlayer) {
+		var message = {type: "kickPlayer", player_id: kickPlayer.playerId};
+		return this._sendRequest(message);
+	}
+
+	// @arg addBot an instance of the type `outgoingMessages.AddBot`
+	sendAddBot(addBot) {
+		var message = {type: "addBot", strategy: addBot.strategy};
+		return this._sendRequest(message);
+	}
+
+	// @arg eliminateSubmission an instance of the type `outgoingMessages.EliminateSubmission`
+	sendEliminateSubmission(eliminateSubmission) {
+		var message = {type: "eliminateSubmission", submission_id: eliminateSubmission.submissionId};
+		return this._sendRequest(message);
+	}
+
+	// @arg hello an instance of the type `outgoingMessages.Hello`
+	sendHello(hello) {
+		var message = {type: "hello", protocol_version: hello.protocolVersion, client: hello.client, last_seq: hello.lastSeq};
+		return this._sendRequest(message);
+	}
+
+	// @arg joinTeam an instance of the type `outgoingMessages.JoinTeam`
+	sendJoinTeam(joinTeam) {
+		var message = {type: "joinTeam", team: joinTeam.team};
+		return this._sendRequest(message);
+	}
+
+	// @arg chat an instance of the type `outgoingMessages.Chat`
+	sendChat(chat) {
+		var message = {type: "chat", text: chat.text};
+		return this._sendRequest(message);
+	}
+
+	// @arg teamChat an instance of the type `outgoingMessages.TeamChat`
+	sendTeamChat(teamChat) {
+		var message = {type: "teamChat", text: teamChat.text};
+		return this._sendRequest(message);
+	}
+
+	// @arg vote an instance of the type `outgoingMessages.Vote`
+	sendVote(vote) {
+		var message = {type: "vote", submission_id: vote.submissionId};
+		return this._sendRequest(message);
+	}
+
+	// @arg rebootHand an instance of the type `outgoingMessages.RebootHand`
+	sendRebootHand(rebootHand) {
+		var message = {type: "rebootHand"};
+		return this._sendRequest(message);
+	}
+
+	// @arg confessCard an instance of the type `outgoingMessages.ConfessCard`
+	sendConfessCard(confessCard) {
+		var message = {type: "confessCard", card_id: confessCard.cardId};
+		return this._sendRequest(message);
+	}
+
+	// @arg transferHost an instance of the type `outgoingMessages.TransferHost`
+	sendTransferHost(transferHost) {
+		var message = {type: "transferHost", player_id: transferHost.playerId};
+		return this._sendRequest(message);
+	}
 
-		this._socketConnection.send(messageJson);
+	// @arg lockMatch an instance of the type `outgoingMessages.LockMatch`
+	sendLockMatch(lockMatch) {
+		var message = {type: "lockMatch", locked: lockMatch.locked};
+		return this._sendRequest(message);
+	}
+
+	// @arg changeSettings an instance of the type `outgoingMessages.ChangeSettings`
+	sendChangeSettings(changeSettings) {
+		var message = {type: "changeSettings", settings: changeSettings.settings};
+		return this._sendRequest(message);
 	}
 
 	//Message handler for socket connection.
@@ -363,6 +834,10 @@ class ServerSocketConnection {
 			console.error("socket connection message type is empty!");
 			return;
 		}
+		// Match events and game states are numbered, so we can ask for what we missed after a reconnect
+		if(typeof jsonData["seq"] === 'number') {
+			this._lastSeq = jsonData["seq"];
+		}
 
 		switch(messageType) {
 			case "playerSubmittedCard":
@@ -372,6 +847,20 @@ class ServerSocketConnection {
 				var message = new incommingMessages.PlayerSubmittedCard(jsonData["user_uuid"], jsonData["card_id"]);
 				this.onPlayerSubmittedCard.dispatch(message);
 			break;
+			case "welcome":
+				if(!validateJsonProperty(jsonData, 'protocol_version', 'number', "Welcome message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'features', 'array', "Welcome message received,")) { return; }
+
+				this._reconnectAttempts = 0;
+				var message = new incommingMessages.Welcome(jsonData["protocol_version"], jsonData["features"], jsonData["player_id"]);
+				this.onWelcome.dispatch(message);
+			break;
+			case "gameState":
+				var message = _jsonToGameState(jsonData);
+				if(message != null) {
+					this.onGameState.dispatch(message);
+				}
+			break;
 			case "addCardToHand":
 				if(!validateJsonProperty(jsonData, 'card_content', 'string', "AddCardToHand message received,")) { return; }
 				if(!validateJsonProperty(jsonData, 'card_id', 'number', "AddCardToHand message received,")) { return; }
@@ -386,16 +875,17 @@ class ServerSocketConnection {
 				this.onRemoveCardFromHand.dispatch(message);
 			break;
 			case "everyone_submitted":
-				if(!validateJsonProperty(jsonData, 'card_ids', 'array', "EveryoneSubmittedCards message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'submission_ids', 'array', "EveryoneSubmittedCards message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'pick', 'number', "EveryoneSubmittedCards message received,")) { return; }
 
-				var message = new incommingMessages.EveryoneSubmittedCards(jsonData["card_ids"]);
+				var message = new incommingMessages.EveryoneSubmittedCards(jsonData["submission_ids"], jsonData["pick"]);
 				this.onEveryoneSubmittedCards.dispatch(message);
 			break;
 			case "revealCard":
-				if(!validateJsonProperty(jsonData, 'card_content', 'string', "RevealCard message received,")) { return; }
-				if(!validateJsonProperty(jsonData, 'card_id', 'number', "RevealCard message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'submission_id', 'number', "RevealCard message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'cards', 'array', "RevealCard message received,")) { return; }
 
-				var message = new incommingMessages.RevealCard(jsonData["card_content"], jsonData["card_id"]);
+				var message = new incommingMessages.RevealCard(jsonData["submission_id"], jsonData["cards"]);
 				this.onRevealCard.dispatch(message);
 			break;
 			case "player_left":
@@ -415,21 +905,25 @@ class ServerSocketConnection {
 				this.onMatchHasStarted.dispatch();
 			break;
 			case "czar_choice":
-				if(!validateJsonProperty(jsonData, 'card_id', 'number', "CzarChoice message received,")) { return; } 
+				if(!validateJsonProperty(jsonData, 'submission_id', 'number', "CzarChoice message received,")) { return; } 
 
-				var message = new incommingMessages.CzarCardChoice(jsonData["card_id"]);
+				var message = new incommingMessages.CzarCardChoice(jsonData["submission_id"], jsonData["cards"]);
 				this.onCzarCardChoice.dispatch(message);
 			break;
-			case "playerWon":
-				if(!validateJsonProperty(jsonData, 'player_id', 'number', "PlayerWon message received,")) { return; }
-				
-				var message = new incommingMessages.PlayerWon(jsonData["player_id"]);
-				this.onPlayerWon.dispatch(message);
+			case "gameOver":
+				if(!validateJsonProperty(jsonData, 'winner', 'number', "GameOver message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'scoreboard', 'array', "GameOver message received,")) { return; }
+
+				var message = new incommingMessages.GameOver(jsonData["winner"], jsonData["scoreboard"], jsonData["winning_team"]);
+				this.onGameOver.dispatch(message);
+			break;
+			case "rematch":
+				this.onRematch.dispatch(new incommingMessages.Rematch());
 			break;
 			case "roundWon":
 				if(!validateJsonProperty(jsonData, 'player_id', 'number', "PlayerRoundWin message received,")) { return; }
 
-				var message = new incommingMessages.PlayerRoundWin(jsonData["player_id"]);
+				var message = new incommingMessages.PlayerRoundWin(jsonData["player_id"], jsonData["team"]);
 				this.onPlayerRoundWin.dispatch(message);
 			break;
 			case "newRound":
@@ -441,42 +935,232 @@ class ServerSocketConnection {
 				var message = new incommingMessages.NewCzar(jsonData["czar"]);
 				this.onNewCzar.dispatch(message);
 			break;
+			case "newHost":
+				if(!validateJsonProperty(jsonData, 'host', 'number', "NewHost message received,")) { return; }
+
+				var message = new incommingMessages.NewHost(jsonData["host"]);
+				this.onNewHost.dispatch(message);
+			break;
+			case "matchDeleted":
+				this.onMatchDeleted.dispatch(new incommingMessages.MatchDeleted());
+			break;
+			case "kicked":
+				this.onKicked.dispatch(new incommingMessages.Kicked());
+			break;
+			case "matchLocked":
+				if(!validateJsonProperty(jsonData, 'locked', 'boolean', "MatchLocked message received,")) { return; }
+
+				var message = new incommingMessages.MatchLocked(jsonData["locked"]);
+				this.onMatchLocked.dispatch(message);
+			break;
+			case "settingsChanged":
+				if(!validateJsonProperty(jsonData, 'settings', 'object', "SettingsChanged message received,")) { return; }
+
+				var message = new incommingMessages.SettingsChanged(jsonData["settings"]);
+				this.onSettingsChanged.dispatch(message);
+			break;
+			case "waitlistPosition":
+				if(!validateJsonProperty(jsonData, 'position', 'number', "WaitlistPosition message received,")) { return; }
+
+				var message = new incommingMessages.WaitlistPosition(jsonData["position"]);
+				this.onWaitlistPosition.dispatch(message);
+			break;
+			case "finalRound":
+				this.onFinalRound.dispatch(new incommingMessages.FinalRound());
+			break;
+			case "eliminationTurn":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "EliminationTurn message received,")) { return; }
+
+				var message = new incommingMessages.EliminationTurn(jsonData["player_id"]);
+				this.onEliminationTurn.dispatch(message);
+			break;
+			case "submissionEliminated":
+				if(!validateJsonProperty(jsonData, 'submission_id', 'number', "SubmissionEliminated message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "SubmissionEliminated message received,")) { return; }
+
+				var message = new incommingMessages.SubmissionEliminated(jsonData["submission_id"], jsonData["player_id"]);
+				this.onSubmissionEliminated.dispatch(message);
+			break;
+			case "teamChanged":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "TeamChanged message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'team', 'number', "TeamChanged message received,")) { return; }
+
+				var message = new incommingMessages.TeamChanged(jsonData["player_id"], jsonData["team"]);
+				this.onTeamChanged.dispatch(message);
+			break;
+			case "teamHands":
+				if(!validateJsonProperty(jsonData, 'hands', 'array', "TeamHands message received,")) { return; }
+
+				var message = new incommingMessages.TeamHands(jsonData["hands"]);
+				this.onTeamHands.dispatch(message);
+			break;
+			case "teamSubmitted":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "TeamSubmitted message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'cards', 'array', "TeamSubmitted message received,")) { return; }
+
+				var message = new incommingMessages.TeamSubmitted(jsonData["player_id"], jsonData["cards"]);
+				this.onTeamSubmitted.dispatch(message);
+			break;
+			case "teamMessage":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "TeamMessage message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'text', 'string', "TeamMessage message received,")) { return; }
+
+				var message = new incommingMessages.TeamMessage(jsonData["player_id"], jsonData["text"]);
+				this.onTeamMessage.dispatch(message);
+			break;
+			case "chat":
+				if(!validateJsonProperty(jsonData, 'text', 'string', "Chat message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'sent_at', 'number', "Chat message received,")) { return; }
+
+				var message = new incommingMessages.Chat(jsonData["player_id"], jsonData["text"], jsonData["sent_at"]);
+				this.onChat.dispatch(message);
+			break;
+			case "voteCast":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "VoteCast message received,")) { return; }
+
+				var message = new incommingMessages.VoteCast(jsonData["player_id"]);
+				this.onVoteCast.dispatch(message);
+			break;
+			case "votesTallied":
+				if(!validateJsonProperty(jsonData, 'votes', 'array', "VotesTallied message received,")) { return; }
+
+				var message = new incommingMessages.VotesTallied(jsonData["votes"]);
+				this.onVotesTallied.dispatch(message);
+			break;
+			case "handRebooted":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "HandRebooted message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'points', 'number', "HandRebooted message received,")) { return; }
+
+				var message = new incommingMessages.HandRebooted(jsonData["player_id"], jsonData["points"]);
+				this.onHandRebooted.dispatch(message);
+			break;
+			case "cardConfessed":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "CardConfessed message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'card', 'object', "CardConfessed message received,")) { return; }
+
+				var message = new incommingMessages.CardConfessed(jsonData["player_id"], jsonData["card"]);
+				this.onCardConfessed.dispatch(message);
+			break;
 			case "newBlack":
 				if(!validateJsonProperty(jsonData, 'card_id', 'number', "NewBlackCard message received,")) { return; }
 				if(!validateJsonProperty(jsonData, 'card_content', 'string', "NewBlackCard message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'pick', 'number', "NewBlackCard message received,")) { return; }
 
-				var message = new incommingMessages.NewBlackCard(jsonData["card_id"], jsonData["card_content"]);
+				var message = new incommingMessages.NewBlackCard(jsonData["card_id"], jsonData["card_content"], jsonData["pick"]);
 				this.onNewBlackCard.dispatch(message);
 			break;
+			case "newPhase":
+				if(!validateJsonProperty(jsonData, 'phase', 'string', "NewPhase message received,")) { return; }
+
+				var message = new incommingMessages.NewPhase(jsonData["phase"], jsonData["deadline"]);
+				this.onNewPhase.dispatch(message);
+			break;
+			case "czarTimedOut":
+				if(!validateJsonProperty(jsonData, 'czar', 'number', "CzarTimedOut message received,")) { return; }
+
+				var message = new incommingMessages.CzarTimedOut(jsonData["czar"]);
+				this.onCzarTimedOut.dispatch(message);
+			break;
+			case "eliminationTimedOut":
+				if(!validateJsonProperty(jsonData, 'player_id', 'number', "EliminationTimedOut message received,")) { return; }
+
+				var message = new incommingMessages.EliminationTimedOut(jsonData["player_id"]);
+				this.onEliminationTimedOut.dispatch(message);
+			break;
+			case "ack":
+				if(!validateJsonProperty(jsonData, 'action', 'string', "Ack message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'request_id', 'number', "Ack message received,")) { return; }
+
+				delete this._pendingRequests[jsonData["request_id"]];
+				var message = new incommingMessages.Ack(jsonData["action"], jsonData["request_id"]);
+				this.onAck.dispatch(message);
+			break;
+			case "error":
+				if(!validateJsonProperty(jsonData, 'action', 'string', "MatchError message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'error', 'string', "MatchError message received,")) { return; }
+				if(!validateJsonProperty(jsonData, 'message', 'string', "MatchError message received,")) { return; }
+
+				delete this._pendingRequests[jsonData["request_id"]];
+				var message = new incommingMessages.MatchError(jsonData["action"], jsonData["request_id"], jsonData["error"], jsonData["message"]);
+				this.onMatchError.dispatch(message);
+			break;
+			case "submitAccepted":
+				if(!validateJsonProperty(jsonData, 'card_ids', 'array', "SubmitAccepted message received,")) { return; }
+
+				var message = new incommingMessages.SubmitAccepted(jsonData["card_ids"]);
+				this.onSubmitAccepted.dispatch(message);
+			break;
 			default:
 				console.error("Unknown message type send by server. Full JSON: " + JSON.stringify(jsonData));
 			break;
 		}
 	}
 
+	// Send a message over the socket with a new request id
+	// @return the request id, the `Ack` or `MatchError` answering the message carries it as well
+	_sendRequest(message) {
+		var requestId = this._nextRequestId++;
+		message.request_id = requestId;
+		this._pendingRequests[requestId] = message.type;
+		this._socketConnection.send(JSON.stringify(message));
+		return requestId;
+	}
+	// @return true while the server didn't answer a message of this type yet
+	isPending(type) {
+		for(var requestId in this._pendingRequests) {
+			if(this._pendingRequests[requestId] == type) {
+				return true;
+			}
+		}
+		return false;
+	}
 	disconnect() {
 		if (this._socketConnection != null) {
 			console.log('Disconnecting...');
+			// We left on purpose, so don't connect again
+			this._socketConnection.onclose = null;
 			this._socketConnection.close();
 			this._socketConnection = null;
 		}
 	}
-	connect(matchId) {
+	// @arg spectate OPTIONAL when true we only watch the match, no JoinMatch is needed beforehand
+	connect(matchId, spectate) {
 		this.disconnect();
-		var wsUri = (window.location.protocol == 'https:' && 'wss://' || 'ws://') + window.location.host + '/ws/' + matchId;
+		// Only the events of the match we were in can be replayed
+		if (matchId != this._matchId || !!spectate != this._spectate) {
+			this._lastSeq = null;
+		}
+		this._matchId = matchId;
+		this._spectate = !!spectate;
+		var wsUri = (window.location.protocol == 'https:' && 'wss://' || 'ws://') + window.location.host + '/ws/' + matchId + (spectate ? '?spectate' : '');
 		this._socketConnection = new WebSocket(wsUri);
 		console.log('Connecting...');
 		var self = this;
 		this._socketConnection.onopen = function () {
 			console.log('Connected.');
+			self.sendHello(new outgoingMessages.Hello(PROTOCOL_VERSION, "CrsH-web", self._lastSeq));
 		};
 		this._socketConnection.onmessage = function (e) {
 			console.log('Received: ' + e.data);
 			self.parseConnectionData(e);
 		};
-		this._socketConnection.onclose = function () {
+		this._socketConnection.onclose = function (e) {
 			console.log('Disconnected.');
-			this.socketConnection = null;
+			self._socketConnection = null;
+			// The answers to these got lost with the socket
+			self._pendingRequests = {};
+			self.onDisconnected.dispatch(new incommingMessages.Disconnected(e.code, e.reason));
+			// The server refused us on purpose, trying again won't help
+			var refused = e.code == CLOSE_CODE_UNSUPPORTED_PROTOCOL || e.code == CLOSE_CODE_HELLO_TIMEOUT || e.code == CLOSE_CODE_CONNECT_FAILED;
+			if (!refused && self._reconnectAttempts < MAX_RECONNECT_ATTEMPTS) {
+				self._reconnectAttempts++;
+				setTimeout(function() {
+					// Unless we connected somewhere else in the meantime
+					if (self._socketConnection == null) {
+						self.connect(self._matchId, self._spectate);
+					}
+				}, RECONNECT_DELAY_MS);
+			}
 		};
 		this._socketConnection.onerror = function(error) {
 			console.error("WebSocket error observed:", error);